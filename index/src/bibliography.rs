@@ -0,0 +1,355 @@
+use crate::extractor::DocumentMetadata;
+use crate::Result;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Entry types which contain no reference.
+const SPECIAL_ENTRY_TYPES: [&str; 3] = ["comment", "preamble", "string"];
+
+/// LaTeX escapes of characters commonly found in (German) names and titles.
+const LATEX_ESCAPES: [(&str, &str); 8] = [
+    ("\\\"a", "ä"),
+    ("\\\"o", "ö"),
+    ("\\\"u", "ü"),
+    ("\\\"A", "Ä"),
+    ("\\\"O", "Ö"),
+    ("\\\"U", "Ü"),
+    ("\\ss", "ß"),
+    ("\\&", "&"),
+];
+
+/// A reference of a BibTeX/BibLaTeX file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BibEntry {
+    pub citekey: String,
+    /// All authors, separated by semicolons.
+    pub author: Option<String>,
+    pub year: Option<u64>,
+    pub title: Option<String>,
+}
+
+impl BibEntry {
+    /// Adds the entry to the metadata of its document. Values of the entry take precedence over
+    /// the ones read from the document.
+    pub fn apply(&self, metadata: &mut DocumentMetadata) {
+        metadata.citekey = Some(self.citekey.clone());
+        if self.author.is_some() {
+            metadata.author = self.author.clone();
+        }
+        if self.year.is_some() {
+            metadata.year = self.year;
+        }
+        if self.title.is_some() {
+            metadata.title = self.title.clone();
+        }
+    }
+}
+
+/// The references of a bib file, by the documents linked in their `file` field.
+#[derive(Clone, Debug, Default)]
+pub struct Bibliography {
+    by_path: HashMap<PathBuf, BibEntry>,
+    /// Fallback for bib files with paths of another machine. `None` if the file name is linked by
+    /// more than one reference.
+    by_file_name: HashMap<OsString, Option<BibEntry>>,
+}
+
+impl Bibliography {
+    /// Loads the given bib file. Relative paths of documents are relative to the bib file.
+    pub fn load(bib_file: &Path) -> Result<Self> {
+        let content = fs::read_to_string(bib_file)?;
+        Ok(Self::parse(
+            &content,
+            bib_file.parent().unwrap_or(Path::new("")),
+        ))
+    }
+
+    pub fn parse(content: &str, base_path: &Path) -> Self {
+        let mut bibliography = Self::default();
+        for (citekey, fields) in parse_entries(content) {
+            let Some(files) = fields.get("file") else {
+                continue;
+            };
+            let entry = BibEntry {
+                citekey,
+                author: fields.get("author").map(|author| {
+                    clean_value(author)
+                        .split(" and ")
+                        .map(str::trim)
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                }),
+                year: fields
+                    .get("year")
+                    .or_else(|| fields.get("date"))
+                    .and_then(|date| date.trim().get(..4))
+                    .and_then(|year| year.parse().ok()),
+                title: fields.get("title").map(|title| clean_value(title)),
+            };
+            for file in split_files(files) {
+                let path = base_path.join(file);
+                if let Some(file_name) = path.file_name() {
+                    bibliography
+                        .by_file_name
+                        .entry(file_name.to_os_string())
+                        .and_modify(|existing| {
+                            if existing.as_ref() != Some(&entry) {
+                                *existing = None;
+                            }
+                        })
+                        .or_insert_with(|| Some(entry.clone()));
+                }
+                let path = fs::canonicalize(&path).unwrap_or(path);
+                bibliography.by_path.insert(path, entry.clone());
+            }
+        }
+        bibliography
+    }
+
+    /// Returns the reference linking to the given document.
+    pub fn get(&self, document: &Path) -> Option<&BibEntry> {
+        let path = fs::canonicalize(document).unwrap_or(document.to_path_buf());
+        match self.by_path.get(&path) {
+            Some(entry) => Some(entry),
+            None => self.by_file_name.get(document.file_name()?)?.as_ref(),
+        }
+    }
+}
+
+/// Returns the citekeys and (lowercase) fields of all references.
+fn parse_entries(content: &str) -> Vec<(String, HashMap<String, String>)> {
+    let mut entries = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find('@') {
+        rest = &rest[start + 1..];
+        let entry_type: String = rest
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        rest = rest[entry_type.len()..].trim_start();
+        let closing = match rest.chars().next() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => continue,
+        };
+        rest = &rest[1..];
+        if SPECIAL_ENTRY_TYPES.contains(&entry_type.as_str()) {
+            let (_, after) = read_braced(rest);
+            rest = after;
+            continue;
+        }
+        let Some(end) = rest.find([',', closing]) else {
+            break;
+        };
+        let citekey = rest[..end].trim().to_string();
+        rest = &rest[end..];
+
+        let mut fields = HashMap::new();
+        loop {
+            rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+            if rest.is_empty() || rest.starts_with(closing) {
+                rest = rest.get(1..).unwrap_or_default();
+                break;
+            }
+            let Some(equals) = rest.find('=') else {
+                rest = "";
+                break;
+            };
+            let name = rest[..equals].trim().to_lowercase();
+            let (value, after) = read_value(&rest[equals + 1..], closing);
+            fields.insert(name, value);
+            rest = after;
+        }
+        if !citekey.is_empty() {
+            entries.push((citekey, fields));
+        }
+    }
+    entries
+}
+
+/// Reads a field value: braced, quoted or bare parts, concatenated with `#`.
+fn read_value(text: &str, closing: char) -> (String, &str) {
+    let mut value = String::new();
+    let mut rest = text.trim_start();
+    loop {
+        if let Some(braced) = rest.strip_prefix('{') {
+            let (part, after) = read_braced(braced);
+            value.push_str(part);
+            rest = after;
+        } else if let Some(quoted) = rest.strip_prefix('"') {
+            let mut depth = 0;
+            let mut end = quoted.len();
+            for (i, c) in quoted.char_indices() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    '"' if depth == 0 => {
+                        end = i;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            value.push_str(&quoted[..end]);
+            rest = quoted.get(end + 1..).unwrap_or_default();
+        } else {
+            let end = rest.find([',', '#', closing]).unwrap_or(rest.len());
+            value.push_str(rest[..end].trim());
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+        match rest.strip_prefix('#') {
+            Some(concatenated) => rest = concatenated.trim_start(),
+            None => return (value, rest),
+        }
+    }
+}
+
+/// Returns the text up to the closing brace matching an opening brace right before the text, and
+/// the text after it.
+fn read_braced(text: &str) -> (&str, &str) {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return (&text[..i], &text[i + 1..]),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    (text, "")
+}
+
+/// Removes LaTeX markup (braces and common escapes) and superfluous whitespace.
+fn clean_value(value: &str) -> String {
+    let mut value = value.replace(['{', '}'], "");
+    for (escape, character) in LATEX_ESCAPES {
+        value = value.replace(escape, character);
+    }
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Splits a `file` field into the paths of the linked files. Besides plain paths, JabRef's and
+/// Mendeley's format (`description:path:type`, `;`-separated, with `\:` and `\;` escaped) is
+/// supported.
+fn split_files(files: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for file in split_unescaped(files, ';') {
+        let parts = split_unescaped(&file, ':');
+        let path = match parts.len() {
+            // Windows paths (C:\...) are escaped, so three or more parts are JabRef's format.
+            3.. => parts[1..parts.len() - 1].join(":"),
+            _ => parts.join(":"),
+        };
+        let path = path
+            .replace("\\:", ":")
+            .replace("\\;", ";")
+            .trim()
+            .to_string();
+        if !path.is_empty() {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn split_unescaped(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == separator && !escaped {
+            parts.push(String::new());
+        } else if let Some(part) = parts.last_mut() {
+            part.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIB: &str = r#"
+@comment{jabref-meta: databaseType:biblatex;}
+@string{ pub = "Mohr" }
+@Book{weber1922,
+  author    = {Weber, Max and M{\"u}ller, Hans},
+  title     = {{Wirtschaft und Gesellschaft}},
+  year      = 1922,
+  publisher = pub # " Siebeck",
+  file      = {:books/weber.pdf:PDF;Scan:scans/weber\:scan.pdf:PDF},
+}
+@article(gramsci1971,
+  title = "Hegemonie {"}und{"} Staat",
+  date = {1971-05-01},
+  file = {/home/user/Zotero/storage/AB12/gramsci.pdf}
+)
+@misc{nofile, title = {No file}}
+"#;
+
+    #[test]
+    fn test_parse() {
+        let bibliography = Bibliography::parse(BIB, Path::new("/library"));
+        let weber = BibEntry {
+            citekey: "weber1922".to_string(),
+            author: Some("Weber, Max; Müller, Hans".to_string()),
+            year: Some(1922),
+            title: Some("Wirtschaft und Gesellschaft".to_string()),
+        };
+        assert_eq!(
+            bibliography.get(Path::new("/library/books/weber.pdf")),
+            Some(&weber)
+        );
+        assert_eq!(
+            bibliography.get(Path::new("/library/scans/weber:scan.pdf")),
+            Some(&weber)
+        );
+        // Documents are found by their file name, if the path does not match.
+        let gramsci = bibliography
+            .get(Path::new("/documents/gramsci.pdf"))
+            .unwrap();
+        assert_eq!(gramsci.citekey, "gramsci1971");
+        assert_eq!(gramsci.title, Some("Hegemonie \"und\" Staat".to_string()));
+        assert_eq!(gramsci.year, Some(1971));
+        assert_eq!(gramsci.author, None);
+        assert_eq!(bibliography.get(Path::new("/library/nofile.pdf")), None);
+    }
+
+    #[test]
+    fn test_split_files() {
+        assert_eq!(
+            split_files("Full Text:C\\:\\\\papers\\\\a.pdf:PDF"),
+            vec!["C:\\\\papers\\\\a.pdf"]
+        );
+        assert_eq!(
+            split_files("/a.pdf;/b.pdf"),
+            vec!["/a.pdf".to_string(), "/b.pdf".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let entry = BibEntry {
+            citekey: "weber1922".to_string(),
+            author: Some("Weber, Max".to_string()),
+            ..Default::default()
+        };
+        let mut metadata = DocumentMetadata {
+            title: Some("Wirtschaft und Gesellschaft".to_string()),
+            author: Some("Unknown".to_string()),
+            ..Default::default()
+        };
+        entry.apply(&mut metadata);
+        assert_eq!(metadata.citekey, Some("weber1922".to_string()));
+        assert_eq!(metadata.author, Some("Weber, Max".to_string()));
+        assert_eq!(
+            metadata.title,
+            Some("Wirtschaft und Gesellschaft".to_string())
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use crate::Result;
+use litt_shared::LITT_DIRECTORY_NAME;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const CONFIG_FILENAME: &str = "config.json";
+const DEFAULT_LINES_PER_PAGE: usize = 100;
+
+/// Backend used to extract the text of PDF documents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfBackend {
+    /// Use pdftotext if it is installed, otherwise the native backend.
+    #[default]
+    Auto,
+    /// Use the pdftotext command-line tool (usually part of xpdf or poppler).
+    Pdftotext,
+    /// Use the built-in pure-Rust extraction.
+    Native,
+}
+
+impl FromStr for PdfBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "pdftotext" => Ok(Self::Pdftotext),
+            "native" => Ok(Self::Native),
+            _ => Err(format!(
+                "unknown pdf backend `{s}` (possible values: auto, pdftotext, native)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PdfBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::Pdftotext => write!(f, "pdftotext"),
+            Self::Native => write!(f, "native"),
+        }
+    }
+}
+
+/// Language of the documents of an index, used to stem words (f.e. "Mystifizierungen" is found
+/// when searching for "Mystifizierung") and to remove stopwords.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl Language {
+    const NAMES: [(Self, &'static str); 18] = [
+        (Self::Arabic, "arabic"),
+        (Self::Danish, "danish"),
+        (Self::Dutch, "dutch"),
+        (Self::English, "english"),
+        (Self::Finnish, "finnish"),
+        (Self::French, "french"),
+        (Self::German, "german"),
+        (Self::Greek, "greek"),
+        (Self::Hungarian, "hungarian"),
+        (Self::Italian, "italian"),
+        (Self::Norwegian, "norwegian"),
+        (Self::Portuguese, "portuguese"),
+        (Self::Romanian, "romanian"),
+        (Self::Russian, "russian"),
+        (Self::Spanish, "spanish"),
+        (Self::Swedish, "swedish"),
+        (Self::Tamil, "tamil"),
+        (Self::Turkish, "turkish"),
+    ];
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let name = s.to_lowercase();
+        Self::NAMES
+            .iter()
+            .find(|(_, language)| *language == name)
+            .map(|(language, _)| *language)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::NAMES.iter().map(|(_, name)| *name).collect();
+                format!(
+                    "unknown language `{s}` (possible values: {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = Self::NAMES
+            .iter()
+            .find(|(language, _)| language == self)
+            .ok_or(fmt::Error)?;
+        write!(f, "{name}")
+    }
+}
+
+impl From<Language> for tantivy::tokenizer::Language {
+    fn from(language: Language) -> Self {
+        match language {
+            Language::Arabic => Self::Arabic,
+            Language::Danish => Self::Danish,
+            Language::Dutch => Self::Dutch,
+            Language::English => Self::English,
+            Language::Finnish => Self::Finnish,
+            Language::French => Self::French,
+            Language::German => Self::German,
+            Language::Greek => Self::Greek,
+            Language::Hungarian => Self::Hungarian,
+            Language::Italian => Self::Italian,
+            Language::Norwegian => Self::Norwegian,
+            Language::Portuguese => Self::Portuguese,
+            Language::Romanian => Self::Romanian,
+            Language::Russian => Self::Russian,
+            Language::Spanish => Self::Spanish,
+            Language::Swedish => Self::Swedish,
+            Language::Tamil => Self::Tamil,
+            Language::Turkish => Self::Turkish,
+        }
+    }
+}
+
+/// How letters with diacritics are folded, so they are found when searching without them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Folding {
+    /// Remove all diacritics (f.e. "Bär" is found by "Bar", "naïve" by "naive").
+    Ascii,
+    /// Write German umlauts as two letters (f.e. "Händel" is found by "Haendel") and remove all
+    /// other diacritics.
+    Umlaut,
+}
+
+impl FromStr for Folding {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ascii" => Ok(Self::Ascii),
+            "umlaut" => Ok(Self::Umlaut),
+            _ => Err(format!(
+                "unknown folding `{s}` (possible values: ascii, umlaut)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Folding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ascii => write!(f, "ascii"),
+            Self::Umlaut => write!(f, "umlaut"),
+        }
+    }
+}
+
+/// Settings of a single index, stored next to the index data in the litt directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    pub pdf_backend: PdfBackend,
+    /// Number of lines after which text and Markdown files start a new (virtual) page, `0` to
+    /// only split Markdown files at headings.
+    pub lines_per_page: usize,
+    /// Whether `.gitignore` files are honoured in addition to `.littignore` files.
+    pub use_gitignore: bool,
+    /// File extensions to index (f.e. `["pdf", "md"]`), all supported extensions if not set.
+    pub extensions: Option<Vec<String>>,
+    /// Whether the text of PDF pages without extractable text (e.g. scans) is recognized with
+    /// tesseract.
+    pub ocr: bool,
+    /// Language(s) tesseract recognizes (f.e. "deu+eng"), tesseract's default if not set.
+    pub ocr_language: Option<String>,
+    /// BibTeX/BibLaTeX file whose entries are linked to documents by their `file` field.
+    pub bib_file: Option<PathBuf>,
+    /// Language used to stem the words of the body, no stemming if not set.
+    pub language: Option<Language>,
+    /// Whether stopwords of the language (f.e. "und", "der") are left out of the index.
+    pub stopwords: bool,
+    /// How letters with diacritics are folded in the body and in queries, no folding if not set.
+    pub folding: Option<Folding>,
+    /// Dictionary file (one word per line) used to split compounds (f.e.
+    /// "Literaturwissenschaft"), so they are found by their parts. Compounds are not split if
+    /// not set.
+    pub compound_dictionary: Option<PathBuf>,
+    /// Whether the n-grams of the body are indexed, so substrings of words can be searched.
+    pub ngrams: bool,
+    /// Number of documents extracted in parallel, one per CPU core if not set.
+    pub workers: Option<usize>,
+    /// Memory (in bytes) the index writer uses for all of its threads, 100 MB (or 15 MB per
+    /// thread, if more) if not set.
+    pub writer_memory: Option<usize>,
+    /// Number of threads of the index writer, one per CPU core (at most 8) if not set.
+    pub writer_threads: Option<usize>,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            pdf_backend: PdfBackend::default(),
+            lines_per_page: DEFAULT_LINES_PER_PAGE,
+            use_gitignore: false,
+            extensions: None,
+            ocr: false,
+            ocr_language: None,
+            bib_file: None,
+            language: None,
+            stopwords: false,
+            folding: None,
+            compound_dictionary: None,
+            ngrams: false,
+            workers: None,
+            writer_memory: None,
+            writer_threads: None,
+        }
+    }
+}
+
+impl IndexConfig {
+    /// Loads the config of the index at the given documents path. Indices created without a
+    /// config use the default config.
+    pub fn load(documents_path: &Path) -> Result<Self> {
+        let path = documents_path
+            .join(LITT_DIRECTORY_NAME)
+            .join(CONFIG_FILENAME);
+        match std::fs::read_to_string(path) {
+            Ok(data) => Ok(serde_json::from_str(&data)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn store(&self, documents_path: &Path) -> Result<()> {
+        let path = documents_path
+            .join(LITT_DIRECTORY_NAME)
+            .join(CONFIG_FILENAME);
+        std::fs::write(path, serde_json::to_string(self)?).map_err(Into::into)
+    }
+}
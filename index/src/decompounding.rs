@@ -0,0 +1,179 @@
+use crate::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tantivy::tokenizer::{Token, TokenFilter, TokenStream, Tokenizer};
+
+/// Words shorter than this are not split.
+const MIN_COMPOUND_CHARS: usize = 6;
+/// Parts shorter than this are not indexed (f.e. "ei" in "Wissenschaftsgeschichte").
+const MIN_PART_CHARS: usize = 4;
+
+/// Words compounds are split into (f.e. "wissenschaft" and "geschichte" for
+/// "Wissenschaftsgeschichte").
+#[derive(Clone, Debug, Default)]
+pub struct CompoundDictionary {
+    words: HashSet<String>,
+    max_word_chars: usize,
+}
+
+impl CompoundDictionary {
+    /// Loads a dictionary file with one word per line.
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::from_words(fs::read_to_string(path)?.lines()))
+    }
+
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let words: HashSet<String> = words
+            .into_iter()
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| word.chars().count() >= MIN_PART_CHARS)
+            .collect();
+        let max_word_chars = words
+            .iter()
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap_or_default();
+        Self {
+            words,
+            max_word_chars,
+        }
+    }
+
+    /// Returns all words of the dictionary contained in the given (lowercase) word, in the order
+    /// they appear. The word itself is not returned.
+    pub fn parts(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let mut parts = Vec::new();
+        if chars.len() < MIN_COMPOUND_CHARS {
+            return parts;
+        }
+        for start in 0..chars.len() {
+            let max_end = chars.len().min(start + self.max_word_chars);
+            for end in (start + MIN_PART_CHARS)..=max_end {
+                if end - start == chars.len() {
+                    continue;
+                }
+                let part: String = chars[start..end].iter().collect();
+                if self.words.contains(&part) {
+                    parts.push(part);
+                }
+            }
+        }
+        parts
+    }
+}
+
+/// Token filter adding the parts of compounds (see [CompoundDictionary::parts]) at the position of
+/// the compound, so they are found like the compound itself. Expects lowercase tokens.
+#[derive(Clone)]
+pub struct DecompoundingFilter(pub Arc<CompoundDictionary>);
+
+impl TokenFilter for DecompoundingFilter {
+    type Tokenizer<T: Tokenizer> = DecompoundingFilterWrapper<T>;
+
+    fn transform<T: Tokenizer>(self, tokenizer: T) -> Self::Tokenizer<T> {
+        DecompoundingFilterWrapper {
+            dictionary: self.0,
+            tokenizer,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DecompoundingFilterWrapper<T> {
+    dictionary: Arc<CompoundDictionary>,
+    tokenizer: T,
+}
+
+impl<T: Tokenizer> Tokenizer for DecompoundingFilterWrapper<T> {
+    type TokenStream<'a> = DecompoundingTokenStream<T::TokenStream<'a>>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        DecompoundingTokenStream {
+            dictionary: self.dictionary.clone(),
+            tail: self.tokenizer.token_stream(text),
+            token: Token::default(),
+            parts: Vec::new(),
+        }
+    }
+}
+
+pub struct DecompoundingTokenStream<T> {
+    dictionary: Arc<CompoundDictionary>,
+    tail: T,
+    /// The current token: a token of the tail or one of its parts.
+    token: Token,
+    /// Parts of the last token of the tail which are still to be returned (in reverse order).
+    parts: Vec<String>,
+}
+
+impl<T: TokenStream> TokenStream for DecompoundingTokenStream<T> {
+    fn advance(&mut self) -> bool {
+        if let Some(part) = self.parts.pop() {
+            // Parts keep the position and offsets of the compound
+            self.token.text = part;
+            return true;
+        }
+        if !self.tail.advance() {
+            return false;
+        }
+        self.token = self.tail.token().clone();
+        self.parts = self.dictionary.parts(&self.token.text);
+        self.parts.reverse();
+        true
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+
+    #[test]
+    fn test_parts() {
+        let dictionary =
+            CompoundDictionary::from_words(["Wissenschaft", "Geschichte", "Literatur", "ei"]);
+        assert_eq!(
+            dictionary.parts("wissenschaftsgeschichte"),
+            vec!["wissenschaft", "geschichte"]
+        );
+        assert_eq!(
+            dictionary.parts("literaturwissenschaft"),
+            vec!["literatur", "wissenschaft"]
+        );
+        assert!(dictionary.parts("wissenschaft").is_empty());
+    }
+
+    #[test]
+    fn test_filter() {
+        let dictionary = CompoundDictionary::from_words(["wissenschaft", "literatur"]);
+        let mut analyzer = TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(LowerCaser)
+            .filter(DecompoundingFilter(Arc::new(dictionary)))
+            .build();
+        let mut stream = analyzer.token_stream("Die Literaturwissenschaft");
+        let mut tokens = Vec::new();
+        while let Some(token) = stream.next() {
+            tokens.push((token.text.clone(), token.position));
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                ("die".to_string(), 0),
+                ("literaturwissenschaft".to_string(), 1),
+                ("literatur".to_string(), 1),
+                ("wissenschaft".to_string(), 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+/// Progress of adding documents to an index, reported to its observer (see
+/// [Index::with_observer](crate::index::Index::with_observer)). Paths are relative to the
+/// documents path of the index. Documents are processed in parallel, so events of different
+/// documents are interleaved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexEvent {
+    /// Checking the given number of documents started.
+    Started { documents: usize },
+    /// A page of a document was extracted and added to the index.
+    PageExtracted { path: String, page: u64, pages: u64 },
+    /// The text of a page could not be extracted. The page is added without text.
+    PageFailed {
+        path: String,
+        page: u64,
+        error: String,
+    },
+    /// A new document was added.
+    Added { path: String, pages: usize },
+    /// A changed document was indexed again.
+    Replaced { path: String, pages: usize },
+    /// A document was found at a new path and re-indexed from its stored pages.
+    Moved { from: String, to: String },
+    /// A document was not indexed again, since it did not change.
+    Skipped { path: String, reason: SkipReason },
+    /// A document could not be indexed.
+    Failed { path: String, error: String },
+    /// The metadata of a document could not be read. The document is indexed without it.
+    MetadataFailed { path: String, error: String },
+    /// A document was removed from the index, since it no longer exists.
+    Removed { path: String },
+    /// All changes were committed. `pages` is the number of pages in the index.
+    Committed {
+        documents: usize,
+        pages: u64,
+        failed: usize,
+        removed: usize,
+    },
+}
+
+impl IndexEvent {
+    /// Whether the event is the last one of a document found in the documents path. Every
+    /// document counted by [IndexEvent::Started] ends with exactly one of these events.
+    pub fn ends_document(&self) -> bool {
+        matches!(
+            self,
+            IndexEvent::Added { .. }
+                | IndexEvent::Replaced { .. }
+                | IndexEvent::Moved { .. }
+                | IndexEvent::Skipped { .. }
+                | IndexEvent::Failed { .. }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Size and modification time did not change.
+    Unchanged,
+    /// The document was modified, but its content is the same.
+    ContentUnchanged,
+}
+
+type Callback = dyn Fn(&IndexEvent) + Send + Sync;
+
+/// Receives the events of an index. The default observer ignores all events.
+#[derive(Clone, Default)]
+pub struct Observer(Option<Arc<Callback>>);
+
+impl Observer {
+    pub fn new(callback: impl Fn(&IndexEvent) + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(callback)))
+    }
+
+    pub fn notify(&self, event: IndexEvent) {
+        if let Some(callback) = &self.0 {
+            callback(&event);
+        }
+    }
+}
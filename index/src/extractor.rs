@@ -0,0 +1,161 @@
+use crate::config::IndexConfig;
+use crate::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+mod epub;
+mod html;
+mod markup;
+mod office;
+mod pdf;
+mod text;
+
+pub use epub::EpubExtractor;
+pub use html::HtmlExtractor;
+pub use office::{DocxExtractor, OdtExtractor};
+pub use pdf::PdfExtractor;
+pub use text::{MarkdownExtractor, TxtExtractor};
+
+/// A page of a document. Documents without real pages (e.g. text files) are split into virtual
+/// pages, which remember where in the document they start.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Page {
+    pub body: String,
+    /// Heading of the section the page belongs to.
+    pub section: Option<String>,
+    /// Line of the document the page starts at (starting at 1).
+    pub line: Option<u64>,
+    /// Why the text of the page could not be extracted, if it could not (f.e. because OCR
+    /// failed). The page is indexed without text.
+    pub error: Option<String>,
+}
+
+impl From<String> for Page {
+    fn from(body: String) -> Self {
+        Self {
+            body,
+            ..Default::default()
+        }
+    }
+}
+
+/// Information about a document as a whole, as far as the extractor can tell.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentMetadata {
+    /// Title to display instead of the file name.
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    /// Year the document was created.
+    pub year: Option<u64>,
+    /// Key of the document in a bibliography (see [crate::bibliography]).
+    pub citekey: Option<String>,
+}
+
+/// Extracts the text of a document, page by page.
+///
+/// Extractors are registered for a file extension (see [ExtractorRegistry]). Every page returned
+/// is added to the index as a tantivy document.
+pub trait Extractor: Send + Sync {
+    /// Returns all pages of the document at the given path (in order).
+    fn extract(&self, path: &Path) -> Result<Vec<Page>>;
+
+    /// Returns the metadata of the document at the given path. Defaults to no metadata.
+    fn metadata(&self, _path: &Path) -> Result<DocumentMetadata> {
+        Ok(DocumentMetadata::default())
+    }
+
+    /// Returns the pages and the metadata of the document at the given path. Documents whose
+    /// metadata can not be read still have pages. Defaults to [extract()](Self::extract) and
+    /// [metadata()](Self::metadata); extractors reading the same source for both override it to
+    /// read the document only once.
+    fn extract_with_metadata(&self, path: &Path) -> Result<(Vec<Page>, Result<DocumentMetadata>)> {
+        Ok((self.extract(path)?, self.metadata(path)))
+    }
+}
+
+/// The extractors used by an index, by (lowercase) file extension.
+#[derive(Clone, Default)]
+pub struct ExtractorRegistry {
+    extractors: HashMap<String, Arc<dyn Extractor>>,
+}
+
+impl ExtractorRegistry {
+    /// Creates a registry with the built-in extractors, set up according to the given config. If
+    /// the config limits the extensions to index, only extractors for these are registered.
+    pub fn from_config(config: &IndexConfig) -> Self {
+        let mut registry = Self::default();
+        let mut pdf_extractor = PdfExtractor::new(config.pdf_backend);
+        if config.ocr {
+            pdf_extractor = pdf_extractor.with_ocr(config.ocr_language.clone());
+        }
+        registry.register("pdf", pdf_extractor);
+        registry.register("epub", EpubExtractor);
+        registry.register("docx", DocxExtractor);
+        registry.register("odt", OdtExtractor);
+        registry.register("html", HtmlExtractor);
+        registry.register("htm", HtmlExtractor);
+        registry.register("md", MarkdownExtractor::new(config.lines_per_page));
+        registry.register("txt", TxtExtractor::new(config.lines_per_page));
+        if let Some(extensions) = &config.extensions {
+            let extensions: Vec<String> = extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase())
+                .collect();
+            registry
+                .extractors
+                .retain(|extension, _| extensions.contains(extension));
+        }
+        registry
+    }
+
+    /// Registers an extractor for the given file extension, replacing any existing one.
+    pub fn register(&mut self, extension: &str, extractor: impl Extractor + 'static) {
+        self.extractors
+            .insert(extension.to_lowercase(), Arc::new(extractor));
+    }
+
+    /// Returns the extractor for the file extension of the given path.
+    pub fn get(&self, path: &Path) -> Option<Arc<dyn Extractor>> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        self.extractors.get(&extension).cloned()
+    }
+
+    pub fn supports(&self, path: &Path) -> bool {
+        self.get(path).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = ExtractorRegistry::from_config(&IndexConfig::default());
+        assert!(registry.supports(Path::new("paper.pdf")));
+        assert!(registry.supports(Path::new("Notes.MD")));
+        assert!(registry.supports(Path::new("notes.txt")));
+        assert!(registry.supports(Path::new("book.epub")));
+        assert!(registry.supports(Path::new("article.htm")));
+        assert!(registry.supports(Path::new("draft.docx")));
+        assert!(registry.supports(Path::new("draft.odt")));
+        assert!(!registry.supports(Path::new("notes.cmd")));
+        assert!(!registry.supports(Path::new("pdf")));
+    }
+
+    #[test]
+    fn test_registry_extensions() {
+        let config = IndexConfig {
+            extensions: Some(vec!["PDF".to_string(), ".md".to_string()]),
+            ..Default::default()
+        };
+        let registry = ExtractorRegistry::from_config(&config);
+        assert!(registry.supports(Path::new("paper.pdf")));
+        assert!(registry.supports(Path::new("notes.md")));
+        assert!(!registry.supports(Path::new("notes.txt")));
+        assert!(!registry.supports(Path::new("book.epub")));
+    }
+}
@@ -0,0 +1,239 @@
+use super::markup::html_to_text;
+use super::{Extractor, Page};
+use crate::LittIndexError::EpubParseError;
+use crate::Result;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+/// Extracts EPUB documents. Every item of the spine (usually a chapter) becomes a page.
+pub struct EpubExtractor;
+
+impl Extractor for EpubExtractor {
+    fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+        let mut archive =
+            ZipArchive::new(File::open(path)?).map_err(|e| EpubParseError(e.to_string()))?;
+        let container = read_entry(&mut archive, CONTAINER_PATH)?;
+        let package_path = package_path(&container)?;
+        let package = read_entry(&mut archive, &package_path)?;
+        let base = package_path
+            .rsplit_once('/')
+            .map_or("", |(directory, _)| directory);
+
+        spine_documents(&package)?
+            .iter()
+            .map(|href| {
+                let chapter = read_entry(&mut archive, &resolve_href(base, href))?;
+                Ok(Page::from(html_to_text(&chapter)))
+            })
+            .collect()
+    }
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| EpubParseError(format!("{}: {}", name, e)))?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Returns the path of the package document (OPF) from the container.
+fn package_path(container: &str) -> Result<String> {
+    let mut reader = Reader::from_str(container);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == b"rootfile" => {
+                if let Some(path) = attribute(&e, "full-path")? {
+                    return Ok(path);
+                }
+            }
+            Ok(Event::Eof) => {
+                return Err(EpubParseError(format!(
+                    "{} contains no rootfile",
+                    CONTAINER_PATH
+                )))
+            }
+            Err(e) => return Err(EpubParseError(e.to_string())),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the hrefs of all spine items of the package document, in reading order.
+fn spine_documents(package: &str) -> Result<Vec<String>> {
+    let mut manifest: HashMap<String, String> = HashMap::new();
+    let mut spine: Vec<String> = Vec::new();
+    let mut reader = Reader::from_str(package);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) => match e.local_name().as_ref() {
+                b"item" => {
+                    if let (Some(id), Some(href)) = (attribute(&e, "id")?, attribute(&e, "href")?) {
+                        manifest.insert(id, href);
+                    }
+                }
+                b"itemref" => {
+                    if let Some(idref) = attribute(&e, "idref")? {
+                        spine.push(idref);
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(EpubParseError(e.to_string())),
+            _ => {}
+        }
+    }
+    spine
+        .iter()
+        .map(|idref| {
+            manifest
+                .get(idref)
+                .cloned()
+                .ok_or_else(|| EpubParseError(format!("spine item not in manifest: {}", idref)))
+        })
+        .collect()
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>> {
+    let attribute = element
+        .try_get_attribute(name)
+        .map_err(|e| EpubParseError(e.to_string()))?;
+    attribute
+        .map(|a| {
+            a.unescape_value()
+                .map(|value| value.to_string())
+                .map_err(|e| EpubParseError(e.to_string()))
+        })
+        .transpose()
+}
+
+/// Resolves an href of the package document to the name of the entry in the archive.
+fn resolve_href(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut segments: Vec<String> = base
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect();
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(percent_decode(segment)),
+        }
+    }
+    segments.join("/")
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    fn write_test_epub(path: &Path) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let files = [
+            ("mimetype", "application/epub+zip"),
+            (
+                CONTAINER_PATH,
+                r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata/>
+  <manifest>
+    <item id="c2" href="text/chapter%202.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c1" href="text/chapter1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="c1"/>
+    <itemref idref="c2"/>
+  </spine>
+</package>"#,
+            ),
+            (
+                "OEBPS/text/chapter1.xhtml",
+                "<html><head><title>One</title></head><body><h1>Kapitel 1</h1><p>Hello world</p></body></html>",
+            ),
+            (
+                "OEBPS/text/chapter 2.xhtml",
+                "<html><body><p>Goodbye &amp; farewell</p></body></html>",
+            ),
+        ];
+        for (name, content) in files {
+            zip.start_file(name, stored).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_extract_epub() {
+        let path = std::env::temp_dir().join("litt_test_extract.epub");
+        write_test_epub(&path);
+        let pages = EpubExtractor.extract(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            vec!["Kapitel 1\nHello world", "Goodbye & farewell"],
+            pages
+                .unwrap()
+                .into_iter()
+                .map(|page| page.body)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_resolve_href() {
+        assert_eq!(
+            "OEBPS/text/a b.xhtml",
+            resolve_href("OEBPS", "text/a%20b.xhtml#x")
+        );
+        assert_eq!("text/a.xhtml", resolve_href("OEBPS", "../text/a.xhtml"));
+        assert_eq!("a.xhtml", resolve_href("", "a.xhtml"));
+    }
+}
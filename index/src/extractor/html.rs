@@ -0,0 +1,120 @@
+use super::markup::{html_title, html_to_text};
+use super::{DocumentMetadata, Extractor, Page};
+use crate::Result;
+use std::fs;
+use std::path::Path;
+
+/// Maximum number of characters of a virtual page. Webpages have no pages, so long ones are split
+/// to let results point to the matching part of the document.
+const MAX_PAGE_CHARS: usize = 3000;
+
+/// Extracts HTML documents (e.g. saved webpages), split into virtual pages.
+pub struct HtmlExtractor;
+
+impl Extractor for HtmlExtractor {
+    fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+        Ok(pages(&read_html(path)?))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<DocumentMetadata> {
+        Ok(metadata(&read_html(path)?))
+    }
+
+    fn extract_with_metadata(&self, path: &Path) -> Result<(Vec<Page>, Result<DocumentMetadata>)> {
+        let html = read_html(path)?;
+        Ok((pages(&html), Ok(metadata(&html))))
+    }
+}
+
+fn pages(html: &str) -> Vec<Page> {
+    let pages = split_into_pages(&html_to_text(html), MAX_PAGE_CHARS);
+    pages.into_iter().map(Page::from).collect()
+}
+
+fn metadata(html: &str) -> DocumentMetadata {
+    DocumentMetadata {
+        title: html_title(html),
+        ..Default::default()
+    }
+}
+
+/// Saved webpages are not always valid UTF-8, which should not stop them from being indexed.
+fn read_html(path: &Path) -> Result<String> {
+    Ok(String::from_utf8_lossy(&fs::read(path)?).to_string())
+}
+
+/// Splits the text into pages of at most `max_chars` characters, preferably at line breaks, else
+/// at whitespace.
+fn split_into_pages(text: &str, max_chars: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut page_chars = 0;
+    for line in text.lines() {
+        for word in line.split(' ') {
+            let word_chars = word.chars().count();
+            if page_chars > 0 && page_chars + 1 + word_chars > max_chars {
+                pages.push(std::mem::take(&mut page));
+                page_chars = 0;
+            } else if !page.is_empty() && !page.ends_with('\n') {
+                page.push(' ');
+                page_chars += 1;
+            }
+            page.push_str(word);
+            page_chars += word_chars;
+        }
+        page.push('\n');
+        page_chars += 1;
+    }
+    if page_chars > 0 || pages.is_empty() {
+        pages.push(page);
+    }
+    pages
+        .into_iter()
+        .map(|page| page.trim_end().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_html() {
+        let path = std::env::temp_dir().join("litt_test_extract.html");
+        fs::write(
+            &path,
+            "<html><head><title>Ein Artikel</title><script>var x = 1;</script></head>\
+            <body><p>Erster Absatz</p><p>Zweiter Absatz</p></body></html>",
+        )
+        .unwrap();
+        let pages = HtmlExtractor.extract(&path).unwrap();
+        let metadata = HtmlExtractor.metadata(&path).unwrap();
+        let both = HtmlExtractor.extract_with_metadata(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(pages, both.0);
+        assert_eq!(metadata, both.1.unwrap());
+        assert_eq!(
+            vec![Page::from("Erster Absatz\nZweiter Absatz".to_string())],
+            pages
+        );
+        assert_eq!(Some("Ein Artikel".to_string()), metadata.title);
+    }
+
+    #[test]
+    fn test_split_into_pages() {
+        assert_eq!(vec![""], split_into_pages("", 10));
+        assert_eq!(vec!["one\ntwo"], split_into_pages("one\ntwo", 10));
+        assert_eq!(
+            vec!["one\ntwo", "six"],
+            split_into_pages("one\ntwo\nsix", 10)
+        );
+        assert_eq!(
+            vec!["one two", "three", "four five", "six"],
+            split_into_pages("one two three\nfour five six", 10)
+        );
+        assert_eq!(
+            vec!["averylongword", "x"],
+            split_into_pages("averylongword x", 10)
+        );
+    }
+}
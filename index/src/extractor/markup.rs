@@ -0,0 +1,255 @@
+/// Elements whose content is never shown as text.
+const SKIPPED_ELEMENTS: [&str; 5] = ["head", "script", "style", "template", "noscript"];
+
+/// Elements that start a new line of text.
+const BLOCK_ELEMENTS: [&str; 27] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "tr",
+    "ul",
+];
+
+/// Converts (X)HTML to plain text: markup, comments and the content of scripts, styles and the
+/// document head are dropped, entities are decoded and block elements are put on separate lines.
+pub(crate) fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        push_text(&mut text, &rest[..start]);
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = skip_past(comment, "-->");
+            continue;
+        }
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            text.push_str(&cdata[..end]);
+            rest = skip_past(cdata, "]]>");
+            continue;
+        }
+        let Some(end) = find_tag_end(rest) else {
+            // Unterminated tag, nothing readable left.
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if !closing && !tag.ends_with('/') && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            rest = skip_element(rest, &name);
+        }
+        if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            text.push('\n');
+        }
+    }
+    push_text(&mut text, rest);
+
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the content of the `<title>` element, if there is a non-empty one.
+pub(crate) fn html_title(html: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets intact.
+    let lowercase = html.to_ascii_lowercase();
+    let start = lowercase.find("<title")?;
+    let start = start + find_tag_end(&html[start..])? + 1;
+    let end = start + lowercase[start..].find("</title")?;
+
+    let mut title = String::new();
+    push_text(&mut title, &html[start..end]);
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Appends the given text node with decoded entities. Line breaks in the source are just
+/// whitespace, so they are replaced by spaces.
+fn push_text(text: &mut String, node: &str) {
+    let mut rest = node;
+    while let Some(start) = rest.find('&') {
+        push_whitespace_normalized(text, &rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end]).map(|c| (c, end)));
+        match decoded {
+            Some((c, end)) => {
+                text.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    push_whitespace_normalized(text, rest);
+}
+
+fn push_whitespace_normalized(text: &mut String, node: &str) {
+    text.extend(
+        node.chars()
+            .map(|c| if c.is_whitespace() { ' ' } else { c }),
+    );
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "shy" => '\u{ad}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "laquo" => '«',
+        "raquo" => '»',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bdquo" => '„',
+        "auml" => 'ä',
+        "ouml" => 'ö',
+        "uuml" => 'ü',
+        "Auml" => 'Ä',
+        "Ouml" => 'Ö',
+        "Uuml" => 'Ü',
+        "szlig" => 'ß',
+        _ => return None,
+    };
+    Some(c)
+}
+
+/// Returns the text after the first occurrence of `end`, or nothing if it does not occur.
+fn skip_past<'a>(text: &'a str, end: &str) -> &'a str {
+    text.find(end).map_or("", |i| &text[i + end.len()..])
+}
+
+/// Returns the text after the closing tag of the given element.
+fn skip_element<'a>(text: &'a str, name: &str) -> &'a str {
+    let Some(start) = find_ignore_ascii_case(text, &format!("</{}", name)) else {
+        return "";
+    };
+    let text = &text[start..];
+    find_tag_end(text).map_or("", |end| &text[end + 1..])
+}
+
+/// Returns the byte offset of the `>` ending the tag at the start of the text. `>` in quoted
+/// attribute values does not end the tag. Other quotes (f.e. apostrophes in text after a stray
+/// `<`) are ignored, and so are values whose quote is never closed.
+fn find_tag_end(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut after_equals = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'>' => return Some(i),
+            b'=' => after_equals = true,
+            quote @ (b'"' | b'\'') if after_equals => {
+                match bytes[i + 1..].iter().position(|&byte| byte == quote) {
+                    Some(len) => i += len + 1,
+                    None => return text.find('>'),
+                }
+                after_equals = false;
+            }
+            byte if byte.is_ascii_whitespace() => {}
+            _ => after_equals = false,
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the byte offset of the first occurrence of the (ASCII) pattern, ignoring ASCII case.
+fn find_ignore_ascii_case(text: &str, pattern: &str) -> Option<usize> {
+    text.as_bytes()
+        .windows(pattern.len())
+        .position(|window| window.eq_ignore_ascii_case(pattern.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<?xml version=\"1.0\"?>\n<!DOCTYPE html>\n<html>\n<head><title>Ignored</title>\
+            <style>p { color: red; }</style></head>\n<body>\n<h1>Kapitel&nbsp;1</h1>\n\
+            <!-- a comment -->\n<p>Die <em>Mystifizierung</em>\n der Ware &amp; ihr\n\
+            Geheimnis.</p><SCRIPT>alert(1)</SCRIPT><p>Zweiter&#x20;Absatz &#8211; &unknown;</p>\
+            <br/>Ende</body></html>";
+        assert_eq!(
+            "Kapitel 1\nDie Mystifizierung der Ware & ihr Geheimnis.\nZweiter Absatz – &unknown;\nEnde",
+            html_to_text(html)
+        );
+    }
+
+    #[test]
+    fn test_quoted_attributes() {
+        let html = "<p title=\"a > b\" data-x='>'>Text</p><script src=\"x\">if (a > b) {}</ScRiPt \
+            data-y=\">\">Ende";
+        assert_eq!("Text\nEnde", html_to_text(html));
+        assert_eq!(Some(8), find_tag_end("<a b='>'>"));
+        assert_eq!(Some(10), find_tag_end("<a b = \">\">"));
+        assert_eq!(Some(8), find_tag_end("<p don't>text"));
+        assert_eq!(Some(7), find_tag_end("<a b='c>d"));
+        assert_eq!("text", html_to_text("<p don't>text"));
+        assert_eq!("a x", html_to_text("a < b's c <b>x</b>"));
+        assert_eq!(Some(5), find_ignore_ascii_case("text </STYLE>", "</style"));
+    }
+
+    #[test]
+    fn test_html_title() {
+        assert_eq!(
+            Some("Das Kapital & mehr".to_string()),
+            html_title("<html><head><TITLE lang=\"de\">\n Das Kapital &amp;\n mehr </TITLE>")
+        );
+        assert_eq!(None, html_title("<title>  </title>"));
+        assert_eq!(None, html_title("<p>No title</p>"));
+    }
+}
@@ -0,0 +1,459 @@
+use super::{Extractor, Page};
+use crate::LittIndexError::OfficeParseError;
+use crate::Result;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Extracts Word documents (docx). Pages are split at page breaks (including the ones Word stores
+/// when saving), or at headings if the document has no page breaks.
+pub struct DocxExtractor;
+
+impl Extractor for DocxExtractor {
+    fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+        let document = read_entry(path, "word/document.xml")?;
+        let pages = paginate(docx_paragraphs(&document)?);
+        Ok(pages.into_iter().map(Page::from).collect())
+    }
+}
+
+/// Extracts OpenDocument texts (odt). Pages are split at page breaks (including the ones
+/// LibreOffice stores when saving), or at headings if the document has no page breaks.
+pub struct OdtExtractor;
+
+impl Extractor for OdtExtractor {
+    fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+        let content = read_entry(path, "content.xml")?;
+        let pages = paginate(odt_paragraphs(&content)?);
+        Ok(pages.into_iter().map(Page::from).collect())
+    }
+}
+
+fn read_entry(path: &Path, name: &str) -> Result<String> {
+    let mut archive =
+        ZipArchive::new(File::open(path)?).map_err(|e| OfficeParseError(e.to_string()))?;
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| OfficeParseError(format!("{}: {}", name, e)))?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+#[derive(Debug, Default)]
+struct Paragraph {
+    text: String,
+    heading: bool,
+    /// Whether the paragraph starts on a new page.
+    page_break: bool,
+}
+
+/// Collects the paragraphs of a document while it is parsed.
+#[derive(Default)]
+struct Paragraphs {
+    paragraphs: Vec<Paragraph>,
+    current: Option<Paragraph>,
+    pending_page_break: bool,
+}
+
+impl Paragraphs {
+    fn start(&mut self) {
+        self.end();
+        self.current = Some(Paragraph {
+            page_break: std::mem::take(&mut self.pending_page_break),
+            ..Default::default()
+        });
+    }
+
+    fn end(&mut self) {
+        if let Some(paragraph) = self.current.take() {
+            self.paragraphs.push(paragraph);
+        }
+    }
+
+    fn push_str(&mut self, text: &str) {
+        if let Some(paragraph) = &mut self.current {
+            paragraph.text.push_str(text);
+        }
+    }
+
+    fn set_heading(&mut self) {
+        if let Some(paragraph) = &mut self.current {
+            paragraph.heading = true;
+        }
+    }
+
+    /// Starts a new page, splitting the current paragraph if it already contains text.
+    fn page_break(&mut self) {
+        match &mut self.current {
+            Some(paragraph) if paragraph.text.trim().is_empty() => paragraph.page_break = true,
+            Some(_) => {
+                self.start();
+                self.set_page_break();
+            }
+            None => self.pending_page_break = true,
+        }
+    }
+
+    fn set_page_break(&mut self) {
+        if let Some(paragraph) = &mut self.current {
+            paragraph.page_break = true;
+        }
+    }
+
+    fn finish(mut self) -> Vec<Paragraph> {
+        self.end();
+        self.paragraphs
+    }
+}
+
+/// Joins paragraphs to pages, split at page breaks or, if there are none, at headings.
+fn paginate(paragraphs: Vec<Paragraph>) -> Vec<String> {
+    let has_page_breaks = paragraphs.iter().any(|p| p.page_break);
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    for paragraph in paragraphs {
+        let new_page = match has_page_breaks {
+            true => paragraph.page_break,
+            false => paragraph.heading,
+        };
+        if new_page && !page.is_empty() {
+            pages.push(std::mem::take(&mut page));
+        }
+        let text = paragraph
+            .text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !text.is_empty() {
+            if !page.is_empty() {
+                page.push('\n');
+            }
+            page.push_str(&text);
+        }
+    }
+    // A trailing page break does not start another page, but a document without text still has
+    // one (empty) page.
+    if !page.is_empty() || pages.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+/// Parses the paragraphs of `word/document.xml`.
+fn docx_paragraphs(document: &str) -> Result<Vec<Paragraph>> {
+    let mut paragraphs = Paragraphs::default();
+    let mut in_run = false;
+    let mut in_text = false;
+    let mut reader = Reader::from_str(document);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"p" => paragraphs.start(),
+                b"r" => in_run = true,
+                b"t" => in_text = true,
+                _ => {}
+            },
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"p" => paragraphs.end(),
+                b"r" => in_run = false,
+                b"t" => in_text = false,
+                _ => {}
+            },
+            Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"pStyle" => {
+                    let style = attribute(&e, b"val")?.unwrap_or_default().to_lowercase();
+                    if ["heading", "title", "berschrift"]
+                        .iter()
+                        .any(|heading| style.contains(heading))
+                    {
+                        paragraphs.set_heading();
+                    }
+                }
+                b"outlineLvl" => paragraphs.set_heading(),
+                b"pageBreakBefore"
+                    if !matches!(attribute(&e, b"val")?.as_deref(), Some("0" | "false")) =>
+                {
+                    paragraphs.set_page_break()
+                }
+                b"lastRenderedPageBreak" => paragraphs.page_break(),
+                b"br" => match attribute(&e, b"type")?.as_deref() {
+                    Some("page") => paragraphs.page_break(),
+                    _ => paragraphs.push_str("\n"),
+                },
+                b"tab" if in_run => paragraphs.push_str("\t"),
+                _ => {}
+            },
+            Ok(Event::Text(text)) if in_text => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| OfficeParseError(e.to_string()))?;
+                paragraphs.push_str(&text);
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(OfficeParseError(e.to_string())),
+            _ => {}
+        }
+    }
+    Ok(paragraphs.finish())
+}
+
+/// Parses the paragraphs and headings of an OpenDocument `content.xml`.
+fn odt_paragraphs(content: &str) -> Result<Vec<Paragraph>> {
+    let mut paragraphs = Paragraphs::default();
+    // Automatic paragraph styles starting or ending a page.
+    let mut break_before_styles = HashSet::new();
+    let mut break_after_styles = HashSet::new();
+    let mut current_style = None;
+    let mut break_after = false;
+    // Paragraphs can be nested (e.g. in footnotes), their text belongs to the outer paragraph.
+    let mut depth = 0;
+    let mut reader = Reader::from_str(content);
+    loop {
+        let (e, empty) = match reader.read_event() {
+            Ok(Event::Start(e)) => (e, false),
+            Ok(Event::Empty(e)) => (e, true),
+            Ok(Event::End(e)) => {
+                match e.local_name().as_ref() {
+                    b"style" => current_style = None,
+                    b"p" | b"h" => {
+                        depth -= 1;
+                        if depth == 0 {
+                            paragraphs.end();
+                            if std::mem::take(&mut break_after) {
+                                paragraphs.page_break();
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            Ok(Event::Text(text)) if depth > 0 => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| OfficeParseError(e.to_string()))?;
+                paragraphs.push_str(&text);
+                continue;
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(OfficeParseError(e.to_string())),
+            _ => continue,
+        };
+        match e.local_name().as_ref() {
+            b"style" if !empty => current_style = attribute(&e, b"name")?,
+            b"paragraph-properties" => {
+                if let Some(style) = &current_style {
+                    if attribute(&e, b"break-before")?.as_deref() == Some("page") {
+                        break_before_styles.insert(style.clone());
+                    }
+                    if attribute(&e, b"break-after")?.as_deref() == Some("page") {
+                        break_after_styles.insert(style.clone());
+                    }
+                }
+            }
+            name @ (b"p" | b"h") if depth == 0 => {
+                let style = attribute(&e, b"style-name")?.unwrap_or_default();
+                if break_before_styles.contains(&style) {
+                    paragraphs.page_break();
+                }
+                paragraphs.start();
+                if name == b"h" {
+                    paragraphs.set_heading();
+                }
+                break_after = break_after_styles.contains(&style);
+                if empty {
+                    paragraphs.end();
+                    // Empty paragraphs (f.e. inserted to start a new page) end pages, too.
+                    if std::mem::take(&mut break_after) {
+                        paragraphs.page_break();
+                    }
+                } else {
+                    depth += 1;
+                }
+            }
+            b"p" | b"h" if !empty => {
+                paragraphs.push_str(" ");
+                depth += 1;
+            }
+            b"soft-page-break" => paragraphs.page_break(),
+            b"s" if depth > 0 => {
+                let count = attribute(&e, b"c")?.and_then(|c| c.parse().ok());
+                paragraphs.push_str(&" ".repeat(count.unwrap_or(1)));
+            }
+            b"tab" if depth > 0 => paragraphs.push_str("\t"),
+            b"line-break" if depth > 0 => paragraphs.push_str("\n"),
+            _ => {}
+        }
+    }
+    Ok(paragraphs.finish())
+}
+
+/// Returns the value of the attribute with the given local name, whatever its namespace prefix.
+fn attribute(element: &BytesStart, local_name: &[u8]) -> Result<Option<String>> {
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(|e| OfficeParseError(e.to_string()))?;
+        if attribute.key.local_name().as_ref() == local_name {
+            let value = attribute
+                .unescape_value()
+                .map_err(|e| OfficeParseError(e.to_string()))?;
+            return Ok(Some(value.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    fn write_zip(path: &Path, name: &str, content: &str) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_extract_docx() {
+        let document = r#"<?xml version="1.0" encoding="UTF-8"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:body>
+    <w:p><w:pPr><w:tabs><w:tab w:val="left" w:pos="720"/></w:tabs></w:pPr>
+      <w:r><w:t>Hello</w:t></w:r><w:r><w:tab/><w:t xml:space="preserve">World &amp; </w:t></w:r>
+      <w:r><w:instrText>PAGE</w:instrText></w:r></w:p>
+    <w:p><w:r><w:t>Still page one,</w:t></w:r><w:r><w:lastRenderedPageBreak/><w:t>page two</w:t></w:r></w:p>
+    <w:p><w:r><w:br w:type="page"/></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Goodbye</w:t></w:r></w:p>
+  </w:body>
+</w:document>"#;
+        let path = std::env::temp_dir().join("litt_test_extract.docx");
+        write_zip(&path, "word/document.xml", document);
+        let pages = DocxExtractor.extract(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            vec!["Hello World &\nStill page one,", "page two", "Goodbye"],
+            pages
+                .unwrap()
+                .into_iter()
+                .map(|page| page.body)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_extract_docx_ending_with_page_break() {
+        let document = r#"<?xml version="1.0" encoding="UTF-8"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:body>
+    <w:p><w:r><w:t>First page</w:t></w:r></w:p>
+    <w:p><w:r><w:t>Last page</w:t><w:br w:type="page"/></w:r></w:p>
+    <w:p><w:r><w:br w:type="page"/></w:r></w:p>
+  </w:body>
+</w:document>"#;
+        let path = std::env::temp_dir().join("litt_test_extract_page_break.docx");
+        write_zip(&path, "word/document.xml", document);
+        let pages = DocxExtractor.extract(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            vec!["First page\nLast page"],
+            pages
+                .unwrap()
+                .into_iter()
+                .map(|page| page.body)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_extract_odt() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0"
+    xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0"
+    xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0"
+    xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0">
+  <office:automatic-styles>
+    <style:style style:name="P1" style:family="paragraph">
+      <style:paragraph-properties fo:break-before="page"/>
+    </style:style>
+  </office:automatic-styles>
+  <office:body><office:text>
+    <text:h text:outline-level="1">Kapitel</text:h>
+    <text:p>Hello<text:s text:c="2"/>World<text:note><text:note-body><text:p>Note</text:p></text:note-body></text:note></text:p>
+    <text:soft-page-break/>
+    <text:p>Second page</text:p>
+    <text:p text:style-name="P1">Third page</text:p>
+  </office:text></office:body>
+</office:document-content>"#;
+        let path = std::env::temp_dir().join("litt_test_extract.odt");
+        write_zip(&path, "content.xml", content);
+        let pages = OdtExtractor.extract(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            vec!["Kapitel\nHello World Note", "Second page", "Third page"],
+            pages
+                .unwrap()
+                .into_iter()
+                .map(|page| page.body)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_extract_odt_empty_paragraph_breaking_after() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0"
+    xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0"
+    xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0"
+    xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0">
+  <office:automatic-styles>
+    <style:style style:name="P1" style:family="paragraph">
+      <style:paragraph-properties fo:break-after="page"/>
+    </style:style>
+  </office:automatic-styles>
+  <office:body><office:text>
+    <text:p>First page</text:p>
+    <text:p text:style-name="P1"/>
+    <text:p>Second page</text:p>
+    <text:p text:style-name="P1"></text:p>
+    <text:p>Third page</text:p>
+  </office:text></office:body>
+</office:document-content>"#;
+        let path = std::env::temp_dir().join("litt_test_extract_break_after.odt");
+        write_zip(&path, "content.xml", content);
+        let pages = OdtExtractor.extract(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            vec!["First page", "Second page", "Third page"],
+            pages
+                .unwrap()
+                .into_iter()
+                .map(|page| page.body)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_paginate_at_headings() {
+        let paragraph = |text: &str, heading| Paragraph {
+            text: text.to_string(),
+            heading,
+            page_break: false,
+        };
+        let paragraphs = vec![
+            paragraph("Intro", false),
+            paragraph("One", true),
+            paragraph("Text", false),
+            paragraph("Two", true),
+        ];
+        assert_eq!(vec!["Intro", "One\nText", "Two"], paginate(paragraphs));
+        assert_eq!(vec![""], paginate(Vec::new()));
+    }
+}
@@ -0,0 +1,367 @@
+use super::{DocumentMetadata, Extractor, Page};
+use crate::config::PdfBackend;
+use crate::LittIndexError::PdfParseError;
+use crate::Result;
+use lopdf::{Dictionary, Document, Object};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+const OCR_SETUP_HINT: &str = "Make sure tesseract and pdftoppm (usually part of poppler) are \
+    installed to recognize the text of scanned pages";
+
+/// Extracts the pages of PDF documents with the given backend. Pages without text (e.g. scans)
+/// can be run through OCR.
+pub struct PdfExtractor {
+    backend: PdfBackend,
+    ocr: bool,
+    ocr_language: Option<String>,
+}
+
+impl PdfExtractor {
+    pub fn new(backend: PdfBackend) -> Self {
+        Self {
+            backend,
+            ocr: false,
+            ocr_language: None,
+        }
+    }
+
+    /// Recognizes the text of pages without extractable text with tesseract, using the given
+    /// tesseract language(s) (f.e. "deu+eng"), or tesseract's default.
+    pub fn with_ocr(mut self, language: Option<String>) -> Self {
+        self.ocr = true;
+        self.ocr_language = language;
+        self
+    }
+}
+
+impl Extractor for PdfExtractor {
+    fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+        let pages = match self.backend {
+            PdfBackend::Auto if tool_available("pdftotext", "-v") => extract_pages_pdftotext(path),
+            PdfBackend::Auto => extract_pages_native(path),
+            PdfBackend::Pdftotext => extract_pages_pdftotext(path),
+            PdfBackend::Native => extract_pages_native(path),
+        }?;
+        let mut pages: Vec<Page> = pages.into_iter().map(Page::from).collect();
+        if self.ocr {
+            ocr_empty_pages(&mut pages, |page_number| {
+                ocr_page(path, page_number, self.ocr_language.as_deref())
+            });
+        }
+        Ok(pages)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<DocumentMetadata> {
+        // pdfinfo is a separate tool, which may be missing (or fail) even if pdftotext works.
+        match self.backend {
+            PdfBackend::Native => metadata_native(path),
+            _ if tool_available("pdfinfo", "-v") => {
+                metadata_pdfinfo(path).or_else(|_| metadata_native(path))
+            }
+            _ => metadata_native(path),
+        }
+    }
+}
+
+/// Checks (once per tool) whether the given tool can be called with the given argument.
+fn tool_available(name: &'static str, arg: &str) -> bool {
+    static AVAILABLE: OnceLock<Mutex<HashMap<&str, bool>>> = OnceLock::new();
+    let mut available = AVAILABLE
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    *available
+        .entry(name)
+        .or_insert_with(|| Command::new(name).arg(arg).output().is_ok())
+}
+
+fn extract_pages_pdftotext(path: &Path) -> Result<Vec<String>> {
+    // extract all pages at once (written to stdout). xpdf defaults to Latin1.
+    let mut pdf_to_text_call = Command::new("pdftotext");
+    pdf_to_text_call
+        .args(["-enc", "UTF-8"])
+        .arg(path.to_string_lossy().to_string())
+        .arg("-");
+    let pdf_to_text_output = pdf_to_text_call.output().map_err(|_| {
+        PdfParseError("Make sure pdftotext is set up correctly and installed (usually part of xpdf (Windows) or poppler (Linux/Mac))".into())
+    })?;
+    if !pdf_to_text_output.status.success() {
+        return Err(PdfParseError(
+            String::from_utf8_lossy(&pdf_to_text_output.stderr).to_string(),
+        ));
+    }
+    // Invalid characters do not cost the document its pages
+    let text = String::from_utf8_lossy(&pdf_to_text_output.stdout);
+    Ok(split_pages(&text).into_iter().map(String::from).collect())
+}
+
+/// Recognizes the text of the pages without text with the given function (called with the page
+/// number). Pages that can not be recognized are kept empty with the error, so they do not cost
+/// the document its other pages.
+fn ocr_empty_pages(pages: &mut [Page], recognize: impl Fn(usize) -> Result<String>) {
+    for (i, page) in pages.iter_mut().enumerate() {
+        if page.body.trim().is_empty() {
+            match recognize(i + 1) {
+                Ok(text) => page.body = text,
+                Err(e) => page.error = Some(e.to_string()),
+            }
+        }
+    }
+}
+
+/// Recognizes the text of the given page with tesseract. The page is rendered to an image by
+/// pdftoppm, which is piped to tesseract.
+fn ocr_page(path: &Path, page_number: usize, language: Option<&str>) -> Result<String> {
+    // Do not render pages which can not be recognized anyway
+    if !tool_available("tesseract", "--version") {
+        return Err(PdfParseError(OCR_SETUP_HINT.into()));
+    }
+    let page_number = page_number.to_string();
+    let mut render = Command::new("pdftoppm")
+        .args(["-f", &page_number, "-l", &page_number, "-r", "300", "-png"])
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|_| PdfParseError(OCR_SETUP_HINT.into()))?;
+    let image = render
+        .stdout
+        .take()
+        .ok_or_else(|| PdfParseError("Could not read the output of pdftoppm".into()))?;
+
+    let mut recognize = Command::new("tesseract");
+    recognize.arg("stdin").arg("stdout");
+    if let Some(language) = language {
+        recognize.arg("-l").arg(language);
+    }
+    let output = match recognize.stdin(Stdio::from(image)).output() {
+        Ok(output) => output,
+        Err(_) => {
+            let _ = render.kill();
+            render.wait()?;
+            return Err(PdfParseError(OCR_SETUP_HINT.into()));
+        }
+    };
+    let rendered = render.wait()?;
+    if !rendered.success() {
+        return Err(PdfParseError(format!(
+            "pdftoppm could not render page {}",
+            page_number
+        )));
+    }
+    if !output.status.success() {
+        return Err(PdfParseError(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\x0c', '\n'])
+        .to_string())
+}
+
+/// Splits the output of pdftotext into pages. pdftotext ends every page with a form feed.
+fn split_pages(text: &str) -> Vec<&str> {
+    let mut pages: Vec<&str> = text.split('\x0c').collect();
+    if pages.last().is_some_and(|page| page.is_empty()) {
+        pages.pop();
+    }
+    pages
+}
+
+/// Reads the document info with pdfinfo (part of xpdf or poppler, like pdftotext).
+fn metadata_pdfinfo(path: &Path) -> Result<DocumentMetadata> {
+    let output = Command::new("pdfinfo")
+        .args(["-enc", "UTF-8", "-isodates"])
+        .arg(path)
+        .output()
+        .map_err(|_| {
+            PdfParseError("Make sure pdfinfo is set up correctly and installed (usually part of xpdf (Windows) or poppler (Linux/Mac))".into())
+        })?;
+    if !output.status.success() {
+        return Err(PdfParseError(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(parse_pdfinfo(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_pdfinfo(info: &str) -> DocumentMetadata {
+    let mut metadata = DocumentMetadata::default();
+    for line in info.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+        match key {
+            "Title" => metadata.title = value,
+            "Author" => metadata.author = value,
+            "Subject" => metadata.subject = value,
+            "Keywords" => metadata.keywords = value,
+            "CreationDate" => metadata.year = value.and_then(|date| creation_year(&date)),
+            _ => {}
+        }
+    }
+    metadata
+}
+
+/// Reads the document info dictionary without external tools.
+fn metadata_native(path: &Path) -> Result<DocumentMetadata> {
+    let document = Document::load(path).map_err(|e| PdfParseError(e.to_string()))?;
+    let info = match document
+        .trailer
+        .get(b"Info")
+        .and_then(|info| document.dereference(info))
+    {
+        Ok((_, Object::Dictionary(info))) => info,
+        _ => return Ok(DocumentMetadata::default()),
+    };
+    let text = |key: &[u8]| info_text(&document, info, key);
+    Ok(DocumentMetadata {
+        title: text(b"Title"),
+        author: text(b"Author"),
+        subject: text(b"Subject"),
+        keywords: text(b"Keywords"),
+        year: text(b"CreationDate").and_then(|date| creation_year(&date)),
+        ..Default::default()
+    })
+}
+
+fn info_text(document: &Document, info: &Dictionary, key: &[u8]) -> Option<String> {
+    let (_, value) = document.dereference(info.get(key).ok()?).ok()?;
+    let value = lopdf::decode_text_string(value).ok()?;
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// Returns the year of a PDF date (`D:20190314101232+01'00'`) or an ISO date (`2019-03-14T...`).
+fn creation_year(date: &str) -> Option<u64> {
+    let date = date.strip_prefix("D:").unwrap_or(date);
+    let year = date.get(..4)?;
+    if year.chars().all(|c| c.is_ascii_digit()) {
+        year.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Extracts the pages without external tools. Like pdftotext, the content of free text
+/// annotations is added to the text of the page they are placed on.
+fn extract_pages_native(path: &Path) -> Result<Vec<String>> {
+    let document = Document::load(path).map_err(|e| PdfParseError(e.to_string()))?;
+    let pages = document
+        .get_pages()
+        .into_iter()
+        .map(|(page_number, page_id)| {
+            // A page which can not be decoded does not end the document early.
+            let mut body = document.extract_text(&[page_number]).unwrap_or_default();
+            for annotation in document.get_page_annotations(page_id).unwrap_or_default() {
+                let is_free_text = annotation
+                    .get(b"Subtype")
+                    .and_then(|subtype| subtype.as_name())
+                    .is_ok_and(|subtype| subtype == b"FreeText");
+                if let Some(contents) = annotation
+                    .get(b"Contents")
+                    .ok()
+                    .filter(|_| is_free_text)
+                    .and_then(|contents| lopdf::decode_text_string(contents).ok())
+                {
+                    body.push_str(&contents);
+                    body.push('\n');
+                }
+            }
+            body
+        })
+        .collect();
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE_PATH: &str = "../resources/test.pdf";
+
+    #[test]
+    fn test_split_pages() {
+        let text = "First page\n\x0cSecond page\n\x0c\x0cFourth page\n\x0c";
+        let pages = split_pages(text);
+        assert_eq!(
+            pages,
+            vec!["First page\n", "Second page\n", "", "Fourth page\n"]
+        );
+        assert!(split_pages("").is_empty());
+    }
+
+    #[test]
+    fn test_extract_pages_native() {
+        let pages = extract_pages_native(Path::new(TEST_FILE_PATH)).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].contains("Salinas River"));
+        assert!(pages[0].contains("Bär Hündin"));
+        assert!(pages[1].contains("flooding"));
+    }
+
+    #[test]
+    fn test_ocr_failing_page() {
+        let mut pages: Vec<Page> = ["Text", "", " ", ""]
+            .into_iter()
+            .map(|body| Page::from(body.to_string()))
+            .collect();
+        ocr_empty_pages(&mut pages, |page_number| match page_number {
+            3 => Err(PdfParseError("unreadable".into())),
+            _ => Ok(format!("Page {}", page_number)),
+        });
+        let bodies: Vec<&str> = pages.iter().map(|page| page.body.as_str()).collect();
+        assert_eq!(vec!["Text", "Page 2", " ", "Page 4"], bodies);
+        let errors: Vec<Option<&str>> = pages.iter().map(|page| page.error.as_deref()).collect();
+        assert_eq!(
+            vec![None, None, Some("Error parsing PDF: `unreadable`"), None],
+            errors
+        );
+    }
+
+    #[test]
+    fn test_metadata_native() {
+        let metadata = metadata_native(Path::new(TEST_FILE_PATH)).unwrap();
+        // Empty entries are no metadata
+        assert_eq!(metadata.title, None);
+        assert_eq!(metadata.author, None);
+        assert_eq!(metadata.year, Some(2023));
+    }
+
+    #[test]
+    fn test_metadata_without_pdfinfo() {
+        // Falls back to the native metadata, if pdfinfo is missing or fails
+        let extractor = PdfExtractor::new(PdfBackend::Pdftotext);
+        let metadata = extractor.metadata(Path::new(TEST_FILE_PATH)).unwrap();
+        assert_eq!(metadata.year, Some(2023));
+    }
+
+    #[test]
+    fn test_parse_pdfinfo() {
+        let info = "Title:           Wirtschaft und Gesellschaft\nSubject:         \n\
+            Keywords:        Soziologie, Herrschaft\nAuthor:          Max Weber\n\
+            CreationDate:    1922-01-01T10:00:00+01\nPages:           2\n";
+        assert_eq!(
+            DocumentMetadata {
+                title: Some("Wirtschaft und Gesellschaft".to_string()),
+                author: Some("Max Weber".to_string()),
+                subject: None,
+                keywords: Some("Soziologie, Herrschaft".to_string()),
+                year: Some(1922),
+                citekey: None,
+            },
+            parse_pdfinfo(info)
+        );
+    }
+
+    #[test]
+    fn test_creation_year() {
+        assert_eq!(Some(2019), creation_year("D:20190314101232+01'00'"));
+        assert_eq!(Some(2019), creation_year("2019-03-14T10:12:32+01"));
+        assert_eq!(None, creation_year("D:"));
+        assert_eq!(None, creation_year("Thu Mar 14"));
+    }
+}
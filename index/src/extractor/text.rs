@@ -0,0 +1,166 @@
+use super::{Extractor, Page};
+use crate::Result;
+use std::fs;
+use std::path::Path;
+
+/// Extracts plain text documents, split into virtual pages of a fixed number of lines.
+pub struct TxtExtractor {
+    lines_per_page: usize,
+}
+
+impl TxtExtractor {
+    /// Creates an extractor splitting documents every `lines_per_page` lines (`0`: never).
+    pub fn new(lines_per_page: usize) -> Self {
+        Self { lines_per_page }
+    }
+}
+
+impl Extractor for TxtExtractor {
+    fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+        Ok(split_lines(
+            &fs::read_to_string(path)?,
+            self.lines_per_page,
+            false,
+        ))
+    }
+}
+
+/// Extracts Markdown documents, split into virtual pages at every heading and additionally every
+/// `lines_per_page` lines. Pages remember the heading of their section.
+pub struct MarkdownExtractor {
+    lines_per_page: usize,
+}
+
+impl MarkdownExtractor {
+    /// Creates an extractor splitting sections every `lines_per_page` lines (`0`: never).
+    pub fn new(lines_per_page: usize) -> Self {
+        Self { lines_per_page }
+    }
+}
+
+impl Extractor for MarkdownExtractor {
+    fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+        Ok(split_lines(
+            &fs::read_to_string(path)?,
+            self.lines_per_page,
+            true,
+        ))
+    }
+}
+
+fn split_lines(text: &str, lines_per_page: usize, markdown: bool) -> Vec<Page> {
+    let mut pages = Vec::new();
+    let mut page: Option<Page> = None;
+    let mut page_lines = 0;
+    let mut section = None;
+    let mut in_code_block = false;
+    for (i, line) in text.lines().enumerate() {
+        let mut heading = None;
+        if markdown {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+            } else if !in_code_block {
+                heading = markdown_heading(line);
+            }
+        }
+        let page_full = lines_per_page > 0 && page_lines >= lines_per_page;
+        if heading.is_some() || page_full || page.is_none() {
+            if heading.is_some() {
+                section = heading;
+            }
+            pages.extend(page.take());
+            page = Some(Page {
+                body: String::new(),
+                section: section.clone(),
+                line: Some(i as u64 + 1),
+                ..Default::default()
+            });
+            page_lines = 0;
+        }
+        if let Some(page) = &mut page {
+            page.body.push_str(line);
+            page.body.push('\n');
+        }
+        page_lines += 1;
+    }
+    pages.extend(page);
+    if pages.is_empty() {
+        pages.push(Page {
+            line: Some(1),
+            ..Default::default()
+        });
+    }
+    pages
+}
+
+/// Returns the text of an ATX heading (`# Heading`).
+fn markdown_heading(line: &str) -> Option<String> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = line.trim_start_matches(' ');
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let heading = rest.trim().trim_end_matches('#').trim_end();
+    Some(heading.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(body: &str, section: Option<&str>, line: u64) -> Page {
+        Page {
+            body: body.to_string(),
+            section: section.map(String::from),
+            line: Some(line),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_split_lines() {
+        assert_eq!(vec![page("", None, 1)], split_lines("", 2, false));
+        assert_eq!(
+            vec![page("a\nb\n", None, 1), page("c\n", None, 3)],
+            split_lines("a\nb\nc", 2, false)
+        );
+        assert_eq!(
+            vec![page("# no heading\nb\nc\n", None, 1)],
+            split_lines("# no heading\nb\nc", 0, false)
+        );
+    }
+
+    #[test]
+    fn test_split_markdown() {
+        let text = "Intro\n# One #\na\nb\nc\n```\n# code\n```\n## Two\n#hashtag";
+        assert_eq!(
+            vec![
+                page("Intro\n", None, 1),
+                page("# One #\na\nb\n", Some("One"), 2),
+                page("c\n```\n# code\n", Some("One"), 5),
+                page("```\n", Some("One"), 8),
+                page("## Two\n#hashtag\n", Some("Two"), 9),
+            ],
+            split_lines(text, 3, true)
+        );
+    }
+
+    #[test]
+    fn test_markdown_heading() {
+        assert_eq!(Some("Title".to_string()), markdown_heading("# Title"));
+        assert_eq!(
+            Some("Title".to_string()),
+            markdown_heading("   ### Title ##")
+        );
+        assert_eq!(Some("".to_string()), markdown_heading("##"));
+        assert_eq!(None, markdown_heading("#hashtag"));
+        assert_eq!(None, markdown_heading("    # indented code"));
+        assert_eq!(None, markdown_heading("####### seven"));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::config::Folding;
+use tantivy::tokenizer::{Token, TokenFilter, TokenStream, Tokenizer};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Folds the letters of the given text according to the folding (see [Folding]).
+pub fn fold(text: &str, folding: Folding) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match (c, folding) {
+            ('ä', Folding::Umlaut) => folded.push_str("ae"),
+            ('ö', Folding::Umlaut) => folded.push_str("oe"),
+            ('ü', Folding::Umlaut) => folded.push_str("ue"),
+            ('Ä', Folding::Umlaut) => folded.push_str("Ae"),
+            ('Ö', Folding::Umlaut) => folded.push_str("Oe"),
+            ('Ü', Folding::Umlaut) => folded.push_str("Ue"),
+            ('ß', _) => folded.push_str("ss"),
+            ('æ', _) => folded.push_str("ae"),
+            ('Æ', _) => folded.push_str("AE"),
+            ('œ', _) => folded.push_str("oe"),
+            ('Œ', _) => folded.push_str("OE"),
+            ('ø', _) => folded.push('o'),
+            ('Ø', _) => folded.push('O'),
+            ('ł', _) => folded.push('l'),
+            ('Ł', _) => folded.push('L'),
+            ('đ', _) => folded.push('d'),
+            ('Đ', _) => folded.push('D'),
+            (c, _) if c.is_ascii() => folded.push(c),
+            // Decompose the letter and drop its diacritics (f.e. "é" is "e" and "´")
+            (c, _) => folded.extend(c.nfd().filter(|c| !is_combining_mark(*c))),
+        }
+    }
+    folded
+}
+
+/// Token filter folding the letters of every token (see [fold]).
+#[derive(Clone)]
+pub struct FoldingFilter(pub Folding);
+
+impl TokenFilter for FoldingFilter {
+    type Tokenizer<T: Tokenizer> = FoldingFilterWrapper<T>;
+
+    fn transform<T: Tokenizer>(self, tokenizer: T) -> Self::Tokenizer<T> {
+        FoldingFilterWrapper {
+            folding: self.0,
+            tokenizer,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FoldingFilterWrapper<T> {
+    folding: Folding,
+    tokenizer: T,
+}
+
+impl<T: Tokenizer> Tokenizer for FoldingFilterWrapper<T> {
+    type TokenStream<'a> = FoldingTokenStream<T::TokenStream<'a>>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        FoldingTokenStream {
+            folding: self.folding,
+            tail: self.tokenizer.token_stream(text),
+        }
+    }
+}
+
+pub struct FoldingTokenStream<T> {
+    folding: Folding,
+    tail: T,
+}
+
+impl<T: TokenStream> TokenStream for FoldingTokenStream<T> {
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+        let token = self.tail.token_mut();
+        if !token.text.is_ascii() {
+            token.text = fold(&token.text, self.folding);
+        }
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(
+            fold("Bär naïve Straße", Folding::Ascii),
+            "Bar naive Strasse"
+        );
+        assert_eq!(fold("Händel Öl über", Folding::Umlaut), "Haendel Oel ueber");
+        assert_eq!(fold("Œuvre façade", Folding::Umlaut), "OEuvre facade");
+        assert_eq!(fold("Москва", Folding::Ascii), "Москва");
+    }
+}
@@ -1,35 +1,83 @@
-use crate::LittIndexError::{PdfParseError, StateError};
+use crate::bibliography::Bibliography;
+use crate::config::IndexConfig;
+use crate::decompounding::{CompoundDictionary, DecompoundingFilter};
+use crate::event::{IndexEvent, Observer, SkipReason};
+use crate::extractor::{DocumentMetadata, Extractor, ExtractorRegistry, Page};
+use crate::folding::{fold, FoldingFilter};
+use crate::normalize::normalize;
+use crate::page_index;
+use crate::LittIndexError::{
+    Cancelled, OpenError, OutdatedIndex, ReadError, StateError, UnsupportedFormat, UpdateError,
+};
 use crate::Result;
-use litt_shared::search_schema::SearchSchema;
+use ignore::{DirEntry, WalkBuilder};
+use litt_shared::search_schema::{
+    SearchSchema, BODY_TOKENIZER, DECOMPOUNDED_TOKENIZER, NGRAM_CHARS, NGRAM_TOKENIZER,
+};
 use litt_shared::LITT_DIRECTORY_NAME;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use rayon::ThreadPoolBuilder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::convert::AsRef;
-use std::fs::{self, create_dir_all, File};
-use std::io::{self, Read};
+use std::fs::{create_dir_all, File};
+use std::io;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
-use tantivy::query::QueryParser;
-use tantivy::schema::{Schema, TantivyDocument};
+use std::time::{Duration, SystemTime};
+use tantivy::collector::DocSetCollector;
+use tantivy::query::{QueryParser, TermQuery};
+use tantivy::schema::{IndexRecordOption, Schema, TantivyDocument, Value};
+use tantivy::tokenizer::{
+    LowerCaser, NgramTokenizer, RemoveLongFilter, SimpleTokenizer, Stemmer, StopWordFilter,
+    TextAnalyzer,
+};
+use tantivy::Term;
 use tantivy::{Index as TantivyIndex, IndexReader, IndexWriter, ReloadPolicy, Searcher};
 use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
-use walkdir::{DirEntry, WalkDir};
 
 const INDEX_DIRECTORY_NAME: &str = "index";
+/// Directory of the page indices of indices created before they were stored with the pages.
 const PAGES_DIRECTORY_NAME: &str = "pages";
+/// Checksum map of indices created before it was stored with every commit.
 const CHECK_SUM_MAP_FILENAME: &str = "checksum.json";
+const LITT_IGNORE_FILENAME: &str = ".littignore";
+const FORMAT_VERSION_FILENAME: &str = "version";
 
-/// The total target memory usage that will be split between a given number of threads
+/// Version of the on-disk format of an index (tantivy schema, pages and checksum map). Increase it
+/// with every change existing indices can not be read with; they are migrated by indexing all
+/// documents again (see [Index::migrate]).
+pub const FORMAT_VERSION: u32 = 8;
+
+/// The total target memory usage that will be split between a given number of threads, unless
+/// set in the config
 const TARGET_MEMORY_BYTES: usize = 100_000_000;
 
+/// The memory every thread of the index writer needs at least.
+pub const WRITER_MEMORY_PER_THREAD_BYTES: usize = 15_000_000;
+
+/// Number of documents after which added documents are committed, so that indexing can be
+/// resumed after it was cancelled or interrupted.
+const COMMIT_INTERVAL: usize = 100;
+/// Coarsest timestamp granularity of common filesystems (FAT stores modification times in steps
+/// of two seconds).
+const MTIME_GRANULARITY: Duration = Duration::from_secs(2);
+
+#[allow(clippy::large_enum_variant)]
 pub enum Index {
     Writing {
         index: TantivyIndex,
         schema: SearchSchema,
         documents_path: PathBuf,
+        config: IndexConfig,
+        extractors: ExtractorRegistry,
+        observer: Observer,
+        cancelled: Arc<AtomicBool>,
+        bibliography: Bibliography,
         writer: IndexWriter,
     },
     Reading {
@@ -37,47 +85,124 @@ pub enum Index {
         schema: SearchSchema,
         reader: IndexReader,
         documents_path: PathBuf,
+        config: IndexConfig,
+        extractors: ExtractorRegistry,
+        observer: Observer,
+        cancelled: Arc<AtomicBool>,
         failed_documents: Vec<String>,
+        removed_documents: Vec<String>,
+        empty_documents: Vec<String>,
     },
 }
 
 pub type PageIndex = HashMap<String, Vec<(u32, u32)>>;
 
+/// Checksum of an indexed document, used to detect new, changed and deleted documents.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DocumentChecksum {
+    pub len: u64,
+    pub modified: SystemTime,
+    /// Id of the document, indexed with every page.
+    pub doc_id: String,
+    /// Sha256 hash of the file content, used to detect touched and moved documents.
+    #[serde(default)]
+    pub hash: Option<String>,
+    /// When the file was hashed.
+    #[serde(default)]
+    pub hashed: Option<SystemTime>,
+}
+
+impl DocumentChecksum {
+    /// Whether the file was last modified well before it was hashed. Otherwise it may have been
+    /// changed again within the timestamp granularity of the filesystem, without changing its
+    /// modification time, so unchanged size and modification time do not prove that the content
+    /// is unchanged.
+    fn hashed_after_modification(&self) -> bool {
+        self.hashed
+            .is_some_and(|hashed| self.modified + MTIME_GRANULARITY <= hashed)
+    }
+}
+
 impl Index {
     pub fn create(path: impl AsRef<Path>, schema: SearchSchema) -> Result<Self> {
+        Self::create_with_config(path, schema, IndexConfig::default())
+    }
+
+    /// Creates a new index with the given config, which is stored with the index and used
+    /// whenever the index is opened again.
+    pub fn create_with_config(
+        path: impl AsRef<Path>,
+        schema: SearchSchema,
+        config: IndexConfig,
+    ) -> Result<Self> {
         let documents_path = PathBuf::from(path.as_ref());
         let index_path = documents_path
             .join(LITT_DIRECTORY_NAME)
             .join(INDEX_DIRECTORY_NAME);
         create_dir_all(&index_path)?;
         let index = Self::create_index(&index_path, schema.schema.clone())?;
-        let writer = Self::build_writer(&index)?;
+        Self::register_tokenizers(&index, &config, Self::load_compound_dictionary(&config)?)?;
+        let writer = Self::build_writer(&index, &config)?;
+        config.store(&documents_path)?;
+        Self::store_format_version(&documents_path)?;
         Ok(Self::Writing {
             documents_path,
+            extractors: ExtractorRegistry::from_config(&config),
+            observer: Observer::default(),
+            cancelled: Arc::default(),
+            bibliography: Self::load_bibliography(&config)?,
+            config,
             index,
             writer,
             schema,
         })
     }
 
-    pub fn open(path: impl AsRef<Path>, schema: SearchSchema) -> Result<Self> {
+    /// Opens an existing index. The search schema is read from the index. Fails with
+    /// [OutdatedIndex] if the index was created by an older version of litt.
+    ///
+    /// The compound dictionary and the bibliography are only needed for indexing, so they are not
+    /// loaded before [update()](Self::update), and searching works even if they are missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
         let documents_path = PathBuf::from(path.as_ref());
         let index_path = documents_path
             .join(LITT_DIRECTORY_NAME)
             .join(INDEX_DIRECTORY_NAME);
         let index = Self::open_tantivy_index(&index_path)?;
+        let version = Self::load_format_version(&documents_path)?;
+        match version.cmp(&FORMAT_VERSION) {
+            Ordering::Less => return Err(OutdatedIndex(version)),
+            Ordering::Greater => {
+                return Err(OpenError(format!(
+                    "Index was created by a newer version of litt (format version {}), please update litt",
+                    version
+                )))
+            }
+            Ordering::Equal => {}
+        }
+        let schema = SearchSchema::from_schema(index.schema())
+            .map_err(|e| OpenError(format!("Index schema does not match: {}", e)))?;
+        let config = IndexConfig::load(&documents_path)?;
+        // Compounds are indexed along with their parts, so queries need not be split.
+        Self::register_tokenizers(&index, &config, None)?;
         let reader = Self::build_reader(&index)?;
         Ok(Self::Reading {
             index,
             schema,
             reader,
             documents_path,
+            extractors: ExtractorRegistry::from_config(&config),
+            observer: Observer::default(),
+            cancelled: Arc::default(),
+            config,
             failed_documents: vec![],
+            removed_documents: vec![],
+            empty_documents: vec![],
         })
     }
 
+    /// Opens the index at the given path or creates it with the given schema, if it does not exist.
     pub fn open_or_create(path: impl AsRef<Path>, schema: SearchSchema) -> Result<Self> {
-        // TODO make search schema parameter optional and load schema from existing index
         let documents_path = PathBuf::from(path.as_ref());
         let index_path = documents_path
             .join(LITT_DIRECTORY_NAME)
@@ -86,64 +211,193 @@ impl Index {
         let index_create_result = Self::create_index(&index_path, schema.schema.clone());
         match index_create_result {
             Ok(index) => {
-                let writer = Self::build_writer(&index)?;
+                let config = IndexConfig::default();
+                Self::register_tokenizers(&index, &config, None)?;
+                let writer = Self::build_writer(&index, &config)?;
+                config.store(&documents_path)?;
+                Self::store_format_version(&documents_path)?;
                 Ok(Self::Writing {
                     documents_path,
+                    extractors: ExtractorRegistry::from_config(&config),
+                    observer: Observer::default(),
+                    cancelled: Arc::default(),
+                    bibliography: Bibliography::default(),
+                    config,
                     index,
                     writer,
                     schema,
                 })
             }
-            Err(_) => Self::open(path, schema),
+            Err(_) => Self::open(path),
         }
     }
 
+    /// Re-creates an index of an outdated format (see [OutdatedIndex]) with its config. All
+    /// documents have to be indexed again (see [add_all_documents()](Self::add_all_documents)).
+    pub fn migrate(path: impl AsRef<Path>) -> Result<Self> {
+        let documents_path = PathBuf::from(path.as_ref());
+        let config = IndexConfig::load(&documents_path)?;
+        let litt_path = documents_path.join(LITT_DIRECTORY_NAME);
+        for directory in [INDEX_DIRECTORY_NAME, PAGES_DIRECTORY_NAME] {
+            match std::fs::remove_dir_all(litt_path.join(directory)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        _ = std::fs::remove_file(litt_path.join(CHECK_SUM_MAP_FILENAME));
+        Self::create_with_config(documents_path, SearchSchema::default(), config)
+    }
+
     /// Add all PDF documents in located in the path this index was created for (see [create()](Self::create)).
+    /// Documents which were indexed before, but no longer exist, are removed from the index,
+    /// unless they were moved (same content found at a new path).
+    ///
+    /// Documents are committed every [COMMIT_INTERVAL] documents. If indexing is cancelled (see
+    /// [with_cancel_flag()](Self::with_cancel_flag)), the documents processed so far are
+    /// committed and [Cancelled] is returned; the next update continues where it stopped.
     pub fn add_all_documents(mut self) -> Result<Self> {
-        let checksum_map = self.open_checksum_map().ok();
+        let checksum_map = self.open_checksum_map().unwrap_or_default();
         let dir_entries = self.collect_document_files();
+        self.observer().notify(IndexEvent::Started {
+            documents: dir_entries.len(),
+        });
+        let vanished_documents = Mutex::new(Self::vanished_documents(&checksum_map, &dir_entries));
+        let vanished_paths: HashSet<String> = vanished_documents.lock()?.keys().cloned().collect();
+        // Moved documents are re-indexed from the pages stored before this run.
+        let stored_pages = match &self {
+            Index::Writing { index, .. } => Self::build_reader(index)?.searcher(),
+            Index::Reading { .. } => return Err(StateError("Writing".to_string())),
+        };
 
         let failed_documents: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let empty_documents: Mutex<Vec<String>> = Mutex::new(vec![]);
 
-        let new_checksum_map: HashMap<_, _> = dir_entries
-            .par_iter()
-            .filter_map(|path| {
-                let key = path.path().to_string_lossy().to_string();
-                let existing_checksum = checksum_map.as_ref().and_then(|map| map.get(&key));
-                match self.process_file(path, existing_checksum) {
-                    Ok(success) => Some(success),
-                    Err(e) => failed_documents.lock().ok().and_then(|mut failed_files| {
-                        failed_files.push(format!("path: {}, error: {}", path.path().display(), e));
-                        None
-                    }),
-                }
-            })
-            .collect();
-
-        self.store_checksum_map(new_checksum_map)?;
+        // The checksum map stored with every commit. Entries of documents which were not
+        // processed yet are kept, since their pages are still in the index.
+        let mut committed_checksum_map = checksum_map.clone();
+        let mut new_checksum_map = HashMap::new();
+        // Extraction workers (0: one per CPU core)
+        let workers = ThreadPoolBuilder::new()
+            .num_threads(self.config().workers.unwrap_or(0))
+            .build()
+            .map_err(|e| UpdateError(e.to_string()))?;
+        for chunk in dir_entries.chunks(COMMIT_INTERVAL) {
+            if self.is_cancelled() {
+                break;
+            }
+            let failed_before = failed_documents.lock()?.len();
+            let processed: HashMap<_, _> = workers.install(|| {
+                chunk
+                    .par_iter()
+                    .filter_map(|path| {
+                        // Documents not started yet are left for the next update.
+                        if self.is_cancelled() {
+                            return None;
+                        }
+                        let key = path.path().to_string_lossy().to_string();
+                        let existing_checksum = checksum_map.get(&key);
+                        match self.process_file(
+                            path,
+                            existing_checksum,
+                            &vanished_documents,
+                            &stored_pages,
+                            &empty_documents,
+                        ) {
+                            Ok(success) => Some(success),
+                            Err(e) => {
+                                self.observer().notify(IndexEvent::Failed {
+                                    path: self.relative_path(path.path()),
+                                    error: e.to_string(),
+                                });
+                                if let Ok(mut failed_files) = failed_documents.lock() {
+                                    failed_files.push(format!(
+                                        "path: {}, error: {}",
+                                        path.path().display(),
+                                        e
+                                    ));
+                                }
+                                // Failed documents keep their entry (f.e. if they could not be
+                                // read for the moment), so their pages are not orphaned. Changed
+                                // documents are still indexed again by the next update.
+                                existing_checksum.map(|checksum| (key, checksum.clone()))
+                            }
+                        }
+                    })
+                    .collect()
+            });
+            // Moved documents were taken out of the vanished documents and are no longer found
+            // at their old path.
+            let vanished = vanished_documents.lock()?;
+            committed_checksum_map
+                .retain(|path, _| !vanished_paths.contains(path) || vanished.contains_key(path));
+            drop(vanished);
+            // Only added, replaced and moved documents (or failed ones, whose pages may have been
+            // deleted) change the index. Everything else is committed at the end.
+            let changed = failed_documents.lock()?.len() > failed_before
+                || processed.iter().any(|(path, checksum)| {
+                    checksum_map.get(path).is_none_or(|existing| {
+                        existing.doc_id != checksum.doc_id || existing.hash != checksum.hash
+                    })
+                });
+            committed_checksum_map.extend(processed.clone());
+            new_checksum_map.extend(processed);
+            if changed {
+                self.commit(&committed_checksum_map)?;
+            }
+        }
 
+        let cancelled = self.is_cancelled();
+        let (removed_documents, checksum_map) = if cancelled {
+            // Vanished documents are removed by the next update.
+            (vec![], committed_checksum_map)
+        } else {
+            let removed_documents = self.remove_documents(vanished_documents.into_inner()?)?;
+            (removed_documents, new_checksum_map)
+        };
+        let documents = checksum_map.len();
         // We need to call .commit() explicitly to force the
         // index_writer to finish processing the documents in the queue,
         // flush the current index to the disk, and advertise
         // the existence of new documents.
+        self.commit(&checksum_map)?;
+
         if let Index::Writing {
             index,
             schema,
             documents_path,
-            mut writer,
+            config,
+            extractors,
+            observer,
+            cancelled: cancel_flag,
+            ..
         } = self
         {
-            writer.commit()?;
             let reader = Self::build_reader(&index)?;
             reader.reload()?;
+            let failed_documents = failed_documents.lock()?.to_vec();
+            observer.notify(IndexEvent::Committed {
+                documents,
+                pages: reader.searcher().num_docs(),
+                failed: failed_documents.len(),
+                removed: removed_documents.len(),
+            });
             self = Index::Reading {
                 index,
                 schema,
                 reader,
                 documents_path,
-                failed_documents: failed_documents.lock()?.to_vec(),
+                config,
+                extractors,
+                observer,
+                cancelled: cancel_flag,
+                failed_documents,
+                removed_documents,
+                empty_documents: empty_documents.into_inner()?,
             };
-            Ok(self)
+            match cancelled {
+                true => Err(Cancelled),
+                false => Ok(self),
+            }
         } else {
             Err(StateError("Writing".to_string()))
         }
@@ -154,14 +408,25 @@ impl Index {
             index,
             documents_path,
             schema,
+            config,
+            extractors,
+            observer,
+            cancelled,
             ..
         } = self
         {
-            let writer = Self::build_writer(&index)?;
+            Self::register_tokenizers(&index, &config, Self::load_compound_dictionary(&config)?)?;
+            let writer = Self::build_writer(&index, &config)?;
+            let bibliography = Self::load_bibliography(&config)?;
             self = Index::Writing {
                 index,
                 schema,
                 documents_path,
+                config,
+                extractors,
+                observer,
+                cancelled,
+                bibliography,
                 writer,
             };
             self.add_all_documents()
@@ -170,6 +435,71 @@ impl Index {
         }
     }
 
+    /// Registers an extractor for documents with the given file extension (replacing the
+    /// built-in extractor, if any). Extractors are not stored with the index, so they must be
+    /// registered again whenever the index is opened.
+    pub fn with_extractor(mut self, extension: &str, extractor: impl Extractor + 'static) -> Self {
+        match &mut self {
+            Index::Writing { extractors, .. } => extractors.register(extension, extractor),
+            Index::Reading { extractors, .. } => extractors.register(extension, extractor),
+        }
+        self
+    }
+
+    /// Reports the progress of adding documents (see [IndexEvent]) to the given callback. Like
+    /// extractors, observers are not stored with the index.
+    pub fn with_observer(mut self, observer: impl Fn(&IndexEvent) + Send + Sync + 'static) -> Self {
+        match &mut self {
+            Index::Writing { observer: o, .. } => *o = Observer::new(observer),
+            Index::Reading { observer: o, .. } => *o = Observer::new(observer),
+        }
+        self
+    }
+
+    /// Stops adding documents as soon as the given flag is set (see
+    /// [add_all_documents()](Self::add_all_documents)).
+    pub fn with_cancel_flag(mut self, cancel_flag: Arc<AtomicBool>) -> Self {
+        match &mut self {
+            Index::Writing { cancelled, .. } => *cancelled = cancel_flag,
+            Index::Reading { cancelled, .. } => *cancelled = cancel_flag,
+        }
+        self
+    }
+
+    /// Extracts the given number of documents in parallel (instead of one per CPU core). Like
+    /// extractors, resources are not stored with the index (see [IndexConfig] to store them).
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.config_mut().workers = Some(workers);
+        self
+    }
+
+    /// Limits the memory (in bytes) the index writer uses for all of its threads. Every thread
+    /// needs at least 15 MB.
+    pub fn with_writer_memory(mut self, memory: usize) -> Result<Self> {
+        self.config_mut().writer_memory = Some(memory);
+        self.rebuild_writer()
+    }
+
+    /// Sets the number of threads of the index writer.
+    pub fn with_writer_threads(mut self, threads: usize) -> Result<Self> {
+        self.config_mut().writer_threads = Some(threads);
+        self.rebuild_writer()
+    }
+
+    pub fn schema(&self) -> &SearchSchema {
+        match self {
+            Index::Writing { schema, .. } => schema,
+            Index::Reading { schema, .. } => schema,
+        }
+    }
+
+    pub fn config(&self) -> &IndexConfig {
+        match self {
+            Index::Writing { config, .. } => config,
+            Index::Reading { config, .. } => config,
+        }
+    }
+
     pub fn failed_documents(&self) -> Result<Vec<String>> {
         if let Index::Reading {
             failed_documents, ..
@@ -181,27 +511,124 @@ impl Index {
         }
     }
 
+    /// Relative paths of all documents removed from the index by the last
+    /// [add_all_documents()](Self::add_all_documents), because they no longer exist.
+    pub fn removed_documents(&self) -> Result<Vec<String>> {
+        if let Index::Reading {
+            removed_documents, ..
+        } = self
+        {
+            Ok(removed_documents.to_vec())
+        } else {
+            Err(StateError("Reading".to_string()))
+        }
+    }
+
+    /// Relative paths of all documents added by the last
+    /// [add_all_documents()](Self::add_all_documents) without any text (e.g. scans, if OCR is not
+    /// enabled).
+    pub fn empty_documents(&self) -> Result<Vec<String>> {
+        if let Index::Reading {
+            empty_documents, ..
+        } = self
+        {
+            Ok(empty_documents.to_vec())
+        } else {
+            Err(StateError("Reading".to_string()))
+        }
+    }
+
+    /// Adds the given file to the index, unless it is unchanged according to the existing
+    /// checksum. New files with the same content as one of the vanished documents are treated as
+    /// moved: the vanished document is taken out of `vanished_documents` and re-indexed at the new
+    /// path from its stored pages (read with `stored_pages`). Added documents without any text
+    /// are added to `empty_documents`.
     pub fn process_file(
         &self,
         path: &DirEntry,
-        existing_checksum: Option<&(u64, SystemTime)>,
-    ) -> Result<(String, (u64, SystemTime))> {
+        existing_checksum: Option<&DocumentChecksum>,
+        vanished_documents: &Mutex<HashMap<String, DocumentChecksum>>,
+        stored_pages: &Searcher,
+        empty_documents: &Mutex<Vec<String>>,
+    ) -> Result<(String, DocumentChecksum)> {
         if let Index::Writing { documents_path, .. } = &self {
             let relative_path = path.path().strip_prefix(documents_path)?;
 
             let str_path = path.path().to_string_lossy().to_string();
-            if !Self::checksum_is_equal(&str_path, existing_checksum).unwrap_or(false) {
-                println!("Adding document: {}", relative_path.to_string_lossy());
-                self.add_document(path)?;
-                Self::calculate_checksum(&str_path)
-            } else {
-                println!(
-                    "Skipped (already exists): {}",
-                    relative_path.to_string_lossy()
-                );
-                // can unwrap because this arm is only entered when existing checksum is not None
-                Ok((str_path, *(existing_checksum.unwrap())))
+            if let Some(checksum) = existing_checksum {
+                // Files modified shortly before they were hashed are hashed again.
+                if Self::checksum_is_equal(&str_path, Some(checksum)).unwrap_or(false)
+                    && (checksum.hash.is_none() || checksum.hashed_after_modification())
+                {
+                    // Documents indexed before content hashes were introduced get one now.
+                    let checksum = match checksum.hash {
+                        Some(_) => (str_path, checksum.clone()),
+                        None => Self::calculate_checksum(&str_path, checksum.doc_id.clone())?,
+                    };
+                    // Only skipped once it did not fail, so every document ends with one event.
+                    self.observer().notify(IndexEvent::Skipped {
+                        path: relative_path.to_string_lossy().to_string(),
+                        reason: SkipReason::Unchanged,
+                    });
+                    return Ok(checksum);
+                }
             }
+
+            let (str_path, new_checksum) = Self::calculate_checksum(&str_path, String::new())?;
+            let doc_id = match existing_checksum {
+                Some(checksum) if checksum.hash == new_checksum.hash => {
+                    self.observer().notify(IndexEvent::Skipped {
+                        path: relative_path.to_string_lossy().to_string(),
+                        reason: SkipReason::ContentUnchanged,
+                    });
+                    checksum.doc_id.clone()
+                }
+                // Keep the id of changed documents, but replace their stale pages.
+                Some(checksum) => {
+                    let (pages, has_text) = self.add_document(path, &checksum.doc_id, true)?;
+                    if !has_text {
+                        empty_documents
+                            .lock()?
+                            .push(relative_path.to_string_lossy().to_string());
+                    }
+                    self.observer().notify(IndexEvent::Replaced {
+                        path: relative_path.to_string_lossy().to_string(),
+                        pages,
+                    });
+                    checksum.doc_id.clone()
+                }
+                None => match Self::take_moved_document(vanished_documents, &new_checksum)? {
+                    Some((old_path, checksum)) => {
+                        self.move_document(path.path(), &checksum.doc_id, stored_pages)?;
+                        self.observer().notify(IndexEvent::Moved {
+                            from: self.relative_path(Path::new(&old_path)),
+                            to: relative_path.to_string_lossy().to_string(),
+                        });
+                        checksum.doc_id
+                    }
+                    None => {
+                        let doc_id = Uuid::new_v4().to_string();
+                        let (pages, has_text) = self.add_document(path, &doc_id, false)?;
+                        if !has_text {
+                            empty_documents
+                                .lock()?
+                                .push(relative_path.to_string_lossy().to_string());
+                        }
+                        self.observer().notify(IndexEvent::Added {
+                            path: relative_path.to_string_lossy().to_string(),
+                            pages,
+                        });
+                        doc_id
+                    }
+                },
+            };
+            Ok((
+                str_path,
+                DocumentChecksum {
+                    doc_id,
+                    ..new_checksum
+                },
+            ))
         } else {
             Err(StateError("Writing".to_string()))
         }
@@ -211,20 +638,19 @@ impl Index {
     pub fn reload(self) -> Result<Self> {
         if let Index::Reading {
             ref index,
-            ref documents_path,
+            ref config,
             ..
         } = self
         {
-            let writer = Self::build_writer(index)?;
+            let mut writer = Self::build_writer(index, config)?;
             writer.delete_all_documents()?;
-            let checksum_map = PathBuf::from(documents_path)
-                .join(LITT_DIRECTORY_NAME)
-                .join(CHECK_SUM_MAP_FILENAME);
-            _ = std::fs::remove_file(checksum_map);
-            self.add_all_documents()
+            writer.commit()?;
+            // Release the writer's lock for the update.
+            writer.wait_merging_threads()?;
         } else {
-            Err(StateError("Reading".to_string()))
+            return Err(StateError("Reading".to_string()));
         }
+        self.update()
     }
 
     pub fn searcher(&self) -> Result<Searcher> {
@@ -235,26 +661,83 @@ impl Index {
         }
     }
 
+    /// Returns a parser for queries on the default fields. If compounds are split, their parts are
+    /// found, too (use `body:<term>` to only find whole words).
     pub fn query_parser(&self) -> Result<QueryParser> {
-        if let Index::Reading { index, schema, .. } = self {
-            Ok(QueryParser::for_index(index, schema.default_fields()))
+        if let Index::Reading {
+            index,
+            schema,
+            config,
+            ..
+        } = self
+        {
+            let mut fields = schema.default_fields();
+            if config.compound_dictionary.is_some() {
+                fields.push(schema.decompounded);
+            }
+            Ok(QueryParser::for_index(index, fields))
         } else {
             Err(StateError("Reading".to_string()))
         }
     }
 
-    pub fn page_index(&self, path: &str) -> Result<PageIndex> {
-        let mut path = PathBuf::from(path);
-        path.set_extension("pageindex");
-        let data_str = fs::read_to_string(path.to_string_lossy().to_string())?;
-        let fast_results: PageIndex = serde_json::from_str(&data_str)?;
-        Ok(fast_results)
-    }
-
     fn create_index(path: &PathBuf, schema: Schema) -> Result<TantivyIndex> {
         TantivyIndex::create_in_dir(path, schema).map_err(Into::into)
     }
 
+    /// Registers the tokenizers of the body, according to the language and folding settings of
+    /// the index. Compounds are split with the given dictionary, if any.
+    fn register_tokenizers(
+        index: &TantivyIndex,
+        config: &IndexConfig,
+        dictionary: Option<Arc<CompoundDictionary>>,
+    ) -> Result<()> {
+        index
+            .tokenizers()
+            .register(BODY_TOKENIZER, Self::body_analyzer(config, None));
+        index.tokenizers().register(
+            DECOMPOUNDED_TOKENIZER,
+            Self::body_analyzer(config, dictionary),
+        );
+        // The text is folded before it is split into n-grams (see add_page()), since a letter may
+        // fold to several (f.e. "ß" to "ss").
+        let ngram_analyzer =
+            TextAnalyzer::builder(NgramTokenizer::new(NGRAM_CHARS, NGRAM_CHARS, false)?)
+                .filter(LowerCaser)
+                .build();
+        index.tokenizers().register(NGRAM_TOKENIZER, ngram_analyzer);
+        Ok(())
+    }
+
+    fn body_analyzer(
+        config: &IndexConfig,
+        dictionary: Option<Arc<CompoundDictionary>>,
+    ) -> TextAnalyzer {
+        let mut analyzer = TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser)
+            .dynamic();
+        if let Some(dictionary) = dictionary {
+            analyzer = analyzer.filter_dynamic(DecompoundingFilter(dictionary));
+        }
+        let stopwords = config
+            .language
+            .filter(|_| config.stopwords)
+            // Not every language has a list of stopwords
+            .and_then(|language| StopWordFilter::new(language.into()));
+        if let Some(stopwords) = stopwords {
+            analyzer = analyzer.filter_dynamic(stopwords);
+        }
+        // Folded before stemming, so that all spellings have the same stem
+        if let Some(folding) = config.folding {
+            analyzer = analyzer.filter_dynamic(FoldingFilter(folding));
+        }
+        if let Some(language) = config.language {
+            analyzer = analyzer.filter_dynamic(Stemmer::new(language.into()));
+        }
+        analyzer.build()
+    }
+
     fn open_tantivy_index(path: &PathBuf) -> Result<TantivyIndex> {
         TantivyIndex::open_in_dir(path).map_err(Into::into)
     }
@@ -267,134 +750,378 @@ impl Index {
             .map_err(Into::into)
     }
 
-    fn build_writer(index: &TantivyIndex) -> Result<IndexWriter> {
-        index.writer(TARGET_MEMORY_BYTES).map_err(Into::into)
+    fn build_writer(index: &TantivyIndex, config: &IndexConfig) -> Result<IndexWriter> {
+        // Many threads need more than the target memory
+        let memory = config.writer_memory.unwrap_or_else(|| {
+            let thread_memory = config
+                .writer_threads
+                .unwrap_or_default()
+                .saturating_mul(WRITER_MEMORY_PER_THREAD_BYTES);
+            thread_memory.max(TARGET_MEMORY_BYTES)
+        });
+        match config.writer_threads {
+            Some(threads) => index.writer_with_num_threads(threads, memory),
+            None => index.writer(memory),
+        }
+        .map_err(Into::into)
     }
 
+    /// Replaces the writer of a writing index with one built with the current config.
+    fn rebuild_writer(self) -> Result<Self> {
+        match self {
+            Index::Writing {
+                index,
+                schema,
+                documents_path,
+                config,
+                extractors,
+                observer,
+                cancelled,
+                bibliography,
+                writer,
+            } => {
+                // Release the lock of the old writer first.
+                drop(writer);
+                let writer = Self::build_writer(&index, &config)?;
+                Ok(Index::Writing {
+                    index,
+                    schema,
+                    documents_path,
+                    config,
+                    extractors,
+                    observer,
+                    cancelled,
+                    bibliography,
+                    writer,
+                })
+            }
+            Index::Reading { .. } => Ok(self),
+        }
+    }
+
+    /// Reads the format version of the index. Indices without a version were created before the
+    /// format was versioned.
+    fn load_format_version(documents_path: &Path) -> Result<u32> {
+        let path = documents_path
+            .join(LITT_DIRECTORY_NAME)
+            .join(FORMAT_VERSION_FILENAME);
+        match std::fs::read_to_string(path) {
+            Ok(version) => version
+                .trim()
+                .parse()
+                .map_err(|_| OpenError(format!("Invalid format version: `{}`", version.trim()))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store_format_version(documents_path: &Path) -> Result<()> {
+        let path = documents_path
+            .join(LITT_DIRECTORY_NAME)
+            .join(FORMAT_VERSION_FILENAME);
+        std::fs::write(path, FORMAT_VERSION.to_string()).map_err(Into::into)
+    }
+
+    fn load_bibliography(config: &IndexConfig) -> Result<Bibliography> {
+        match &config.bib_file {
+            Some(bib_file) => Bibliography::load(bib_file).map_err(|e| {
+                ReadError(format!(
+                    "Could not read the bibliography {} (bib_file in the index config): {}",
+                    bib_file.display(),
+                    e
+                ))
+            }),
+            None => Ok(Bibliography::default()),
+        }
+    }
+
+    fn load_compound_dictionary(config: &IndexConfig) -> Result<Option<Arc<CompoundDictionary>>> {
+        match &config.compound_dictionary {
+            Some(path) => match CompoundDictionary::load(path) {
+                Ok(dictionary) => Ok(Some(Arc::new(dictionary))),
+                Err(e) => Err(ReadError(format!(
+                    "Could not read the compound dictionary {} (compound_dictionary in the index \
+                     config): {}",
+                    path.display(),
+                    e
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn extractors(&self) -> &ExtractorRegistry {
+        match self {
+            Index::Writing { extractors, .. } => extractors,
+            Index::Reading { extractors, .. } => extractors,
+        }
+    }
+
+    fn config_mut(&mut self) -> &mut IndexConfig {
+        match self {
+            Index::Writing { config, .. } => config,
+            Index::Reading { config, .. } => config,
+        }
+    }
+
+    fn observer(&self) -> &Observer {
+        match self {
+            Index::Writing { observer, .. } => observer,
+            Index::Reading { observer, .. } => observer,
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        match self {
+            Index::Writing { cancelled, .. } => cancelled.load(AtomicOrdering::Relaxed),
+            Index::Reading { cancelled, .. } => cancelled.load(AtomicOrdering::Relaxed),
+        }
+    }
+
+    /// Returns the given path relative to the documents path (or the path itself, if it is not
+    /// in the documents path).
+    fn relative_path(&self, path: &Path) -> String {
+        let documents_path = match self {
+            Index::Writing { documents_path, .. } => documents_path,
+            Index::Reading { documents_path, .. } => documents_path,
+        };
+        path.strip_prefix(documents_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Collects all files with an extension for which an extractor is registered. Files matched
+    /// by a `.littignore` (or, if enabled, a `.gitignore`) file are skipped.
     fn collect_document_files(&self) -> Vec<DirEntry> {
         let documents_path = match self {
             Index::Writing { documents_path, .. } => documents_path,
             Index::Reading { documents_path, .. } => documents_path,
         };
-        let walk_dir = WalkDir::new(documents_path);
-        walk_dir
+        let use_gitignore = self.config().use_gitignore;
+        WalkBuilder::new(documents_path)
             .follow_links(true)
-            .into_iter()
+            .hidden(false)
+            .parents(false)
+            .ignore(false)
+            .git_global(false)
+            .git_exclude(use_gitignore)
+            .git_ignore(use_gitignore)
+            .require_git(false)
+            .add_custom_ignore_filename(LITT_IGNORE_FILENAME)
+            .filter_entry(|entry| entry.file_name() != LITT_DIRECTORY_NAME)
+            .build()
             .filter_map(|entry_result| entry_result.ok())
             .filter(|entry| {
-                entry.file_name().to_string_lossy().ends_with("pdf")
-                    || entry.file_name().to_string_lossy().ends_with("md")
-                    || entry.file_name().to_string_lossy().ends_with("txt")
+                entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file())
+                    && self.extractors().supports(entry.path())
             })
             .collect::<Vec<_>>()
     }
 
-    /// Add a tantivy document to the index for each page of the document.
-    fn add_document(&self, dir_entry: &DirEntry) -> Result<()> {
+    /// Collects all documents, which are stored in the checksum map but were not found in the
+    /// documents path anymore.
+    fn vanished_documents(
+        checksum_map: &HashMap<String, DocumentChecksum>,
+        dir_entries: &[DirEntry],
+    ) -> HashMap<String, DocumentChecksum> {
+        let existing_paths: HashSet<String> = dir_entries
+            .iter()
+            .map(|entry| entry.path().to_string_lossy().to_string())
+            .collect();
+        checksum_map
+            .iter()
+            .filter(|(path, _)| !existing_paths.contains(*path))
+            .map(|(path, checksum)| (path.to_string(), checksum.clone()))
+            .collect()
+    }
+
+    /// Takes the vanished document with the same content hash as the given checksum (if any).
+    /// Returns its old path and checksum.
+    fn take_moved_document(
+        vanished_documents: &Mutex<HashMap<String, DocumentChecksum>>,
+        checksum: &DocumentChecksum,
+    ) -> Result<Option<(String, DocumentChecksum)>> {
+        if checksum.hash.is_none() {
+            return Ok(None);
+        }
+        let mut vanished_documents = vanished_documents.lock()?;
+        let old_path = vanished_documents
+            .iter()
+            .find(|(_, vanished)| vanished.hash == checksum.hash)
+            .map(|(path, _)| path.to_string());
+        Ok(old_path.and_then(|path| vanished_documents.remove_entry(&path)))
+    }
+
+    /// Removes all pages of the given documents from the index and deletes their pages
+    /// directories. Returns the relative paths of the removed documents.
+    fn remove_documents(
+        &self,
+        documents: HashMap<String, DocumentChecksum>,
+    ) -> Result<Vec<String>> {
         if let Index::Writing { documents_path, .. } = self {
-            // Create custom directory to store all pages:
-            let doc_id = Uuid::new_v4();
-            let pages_path = documents_path
-                .join(LITT_DIRECTORY_NAME)
-                .join(PAGES_DIRECTORY_NAME)
-                .join(doc_id.to_string());
-            create_dir_all(&pages_path)?;
-            let full_path = dir_entry.path();
+            let mut removed_documents = vec![];
+            for (path, checksum) in documents {
+                self.delete_pages(&checksum.doc_id)?;
+                let relative_path = Path::new(&path)
+                    .strip_prefix(documents_path)
+                    .unwrap_or(Path::new(&path));
+                self.observer().notify(IndexEvent::Removed {
+                    path: relative_path.to_string_lossy().to_string(),
+                });
+                removed_documents.push(relative_path.to_string_lossy().to_string());
+            }
+            Ok(removed_documents)
+        } else {
+            Err(StateError("Writing".to_string()))
+        }
+    }
 
-            // Check filetype (pdf/ txt)
-            let num = if full_path.to_string_lossy().ends_with("pdf") {
-                self.add_pdf_document(dir_entry, pages_path, full_path)?
-            } else {
-                self.add_txt_document(dir_entry, pages_path, full_path)?
-            };
-            println!(
-                "{} loaded {} page{} at {}",
-                dir_entry.path().to_string_lossy(),
-                num,
-                if num != 1 { "s" } else { "" },
-                full_path.to_string_lossy()
-            );
+    /// Deletes all pages of the document with the given id from the index. Pages added afterwards
+    /// with the same id are not affected.
+    fn delete_pages(&self, doc_id: &str) -> Result<()> {
+        if let Index::Writing { schema, writer, .. } = self {
+            writer.delete_term(Term::from_field_text(schema.doc_id, doc_id));
             Ok(())
         } else {
             Err(StateError("Writing".to_string()))
         }
     }
 
-    fn add_pdf_document(
+    /// Add a tantivy document to the index for each page of the document. Returns the number of
+    /// pages and whether the document contains any text. The stale pages of a replaced document
+    /// are only deleted once the document was extracted, so it keeps them if extraction fails.
+    fn add_document(
         &self,
         dir_entry: &DirEntry,
-        pages_path: PathBuf,
-        full_path: &Path,
-    ) -> Result<u64> {
-        // loop over pages
-        let mut pdf_to_text_successful = true;
-        let mut page_number = 0;
-
-        while pdf_to_text_successful {
-            page_number += 1;
-            // finalize page output path (to the location where all pages are stored)
-            let mut page_path = pages_path.join(page_number.to_string());
-            page_path.set_extension("pageinfo");
-            // get page body
-            let mut pdf_to_text_call = Command::new("pdftotext");
-            pdf_to_text_call
-                .arg("-f")
-                .arg(format!("{}", page_number))
-                .arg("-l")
-                .arg(format!("{}", page_number))
-                .arg(full_path.to_string_lossy().to_string())
-                .arg(page_path.to_string_lossy().to_string());
-
-            let pdf_to_text_output = pdf_to_text_call.output().map_err(|_| {
-                PdfParseError("Make sure pdftotext is set up correctly and installed (usually part of xpdf (Windows) or poppler (Linux/Mac))".into())
-            })?;
-            pdf_to_text_successful = pdf_to_text_output.status.success();
-
-            if pdf_to_text_successful {
-                // read page-body from generated .txt file
-                let page_body = std::fs::read_to_string(&page_path)?;
-                self.add_page(dir_entry.path(), page_number, &page_path, &page_body)?;
-                Self::store_page_index(&page_path.clone(), Self::create_page_index(&page_body)?)?;
-            }
-        }
-
-        Ok(page_number)
-    }
-
-    fn add_txt_document(
+        doc_id: &str,
+        replace: bool,
+    ) -> Result<(usize, bool)> {
+        if let Index::Writing { .. } = self {
+            let full_path = dir_entry.path();
+
+            let extractor = self
+                .extractors()
+                .get(full_path)
+                .ok_or_else(|| UnsupportedFormat(full_path.to_string_lossy().to_string()))?;
+            let (mut pages, metadata) = extractor.extract_with_metadata(full_path)?;
+            for page in &mut pages {
+                page.body = normalize(&page.body);
+            }
+            let metadata = self.document_metadata(metadata, full_path);
+            if replace {
+                self.delete_pages(doc_id)?;
+            }
+            // loop over pages
+            for (i, page) in pages.iter().enumerate() {
+                let page_number = i as u64 + 1;
+                let page_index = page_index::encode(&Self::create_page_index(&page.body)?);
+                self.add_page(full_path, doc_id, &metadata, page_number, &page_index, page)?;
+                if let Some(error) = &page.error {
+                    self.observer().notify(IndexEvent::PageFailed {
+                        path: self.relative_path(full_path),
+                        page: page_number,
+                        error: error.clone(),
+                    });
+                }
+                self.observer().notify(IndexEvent::PageExtracted {
+                    path: self.relative_path(full_path),
+                    page: page_number,
+                    pages: pages.len() as u64,
+                });
+            }
+            Ok((
+                pages.len(),
+                pages.iter().any(|page| !page.body.trim().is_empty()),
+            ))
+        } else {
+            Err(StateError("Writing".to_string()))
+        }
+    }
+
+    /// Re-indexes the stored pages of the document with the given id for its new path, without
+    /// extracting the pages again.
+    fn move_document(&self, full_path: &Path, doc_id: &str, searcher: &Searcher) -> Result<()> {
+        if let Index::Writing { schema, .. } = self {
+            let query = TermQuery::new(
+                Term::from_field_text(schema.doc_id, doc_id),
+                IndexRecordOption::Basic,
+            );
+            let addresses = searcher.search(&query, &DocSetCollector)?;
+            self.delete_pages(doc_id)?;
+            let metadata = match self.extractors().get(full_path) {
+                Some(extractor) => self.document_metadata(extractor.metadata(full_path), full_path),
+                None => DocumentMetadata::default(),
+            };
+            for address in addresses {
+                let stored: TantivyDocument = searcher.doc(address)?;
+                let page_number = stored
+                    .get_first(schema.page)
+                    .and_then(|page| page.as_u64())
+                    .unwrap_or_default();
+                let page_index = stored
+                    .get_first(schema.page_index)
+                    .and_then(|page_index| page_index.as_bytes())
+                    .unwrap_or_default();
+                let page = Page {
+                    body: stored
+                        .get_first(schema.body)
+                        .and_then(|body| body.as_str())
+                        .map(String::from)
+                        .unwrap_or_default(),
+                    section: stored
+                        .get_first(schema.section)
+                        .and_then(|section| section.as_str())
+                        .map(String::from),
+                    line: stored.get_first(schema.line).and_then(|line| line.as_u64()),
+                    ..Default::default()
+                };
+                self.add_page(full_path, doc_id, &metadata, page_number, page_index, &page)?;
+            }
+            Ok(())
+        } else {
+            Err(StateError("Writing".to_string()))
+        }
+    }
+
+    /// Returns the metadata read from the document, completed by its entry in the linked
+    /// bibliography. Documents whose metadata can not be read (f.e. because of a broken PDF info dictionary)
+    /// are indexed without it.
+    fn document_metadata(
         &self,
-        dir_entry: &DirEntry,
-        pages_path: PathBuf,
+        metadata: Result<DocumentMetadata>,
         full_path: &Path,
-    ) -> Result<u64> {
-        let page_number = 1;
-        let mut page_path = pages_path.join(page_number.to_string());
-        page_path.set_extension("pageinfo");
-        // Open the file in read-only mode
-        let mut file = File::open(full_path)?;
-        // Store as page seperatly
-        let mut destination_file = File::create(page_path.clone())?;
-        io::copy(&mut file, &mut destination_file)?;
-        // Read the contents of the file into a string
-        let mut file = File::open(full_path)?;
-        let mut body = String::new();
-        file.read_to_string(&mut body)?;
-        // Finally, add page
-        self.add_page(dir_entry.path(), page_number, &page_path, &body)?;
-        Self::store_page_index(&page_path.clone(), Self::create_page_index(&body)?)?;
-        Ok(page_number)
+    ) -> DocumentMetadata {
+        let mut metadata = metadata.unwrap_or_else(|e| {
+            self.observer().notify(IndexEvent::MetadataFailed {
+                path: self.relative_path(full_path),
+                error: e.to_string(),
+            });
+            DocumentMetadata::default()
+        });
+        if let Index::Writing { bibliography, .. } = self {
+            if let Some(entry) = bibliography.get(full_path) {
+                entry.apply(&mut metadata);
+            }
+        }
+        metadata
     }
 
     fn add_page(
         &self,
         full_path: &Path,
+        doc_id: &str,
+        metadata: &DocumentMetadata,
         page_number: u64,
-        page_path: &Path,
-        page_body: &str,
+        page_index: &[u8],
+        page: &Page,
     ) -> Result<()> {
         if let Index::Writing {
             documents_path,
             schema,
+            config,
             writer,
             ..
         } = self
@@ -404,10 +1131,47 @@ impl Index {
             let mut tantivy_document = TantivyDocument::new();
 
             // add fields to tantivy document
-            tantivy_document.add_text(schema.path, page_path.to_string_lossy());
             tantivy_document.add_text(schema.title, relative_path.to_string_lossy());
             tantivy_document.add_u64(schema.page, page_number);
-            tantivy_document.add_text(schema.body, page_body);
+            tantivy_document.add_text(schema.body, &page.body);
+            tantivy_document.add_bytes(schema.page_index, page_index);
+            if config.compound_dictionary.is_some() {
+                tantivy_document.add_text(schema.decompounded, &page.body);
+            }
+            if config.ngrams {
+                // Substrings spanning line breaks are searched with spaces
+                let body = match config.folding {
+                    Some(folding) => fold(&page.body, folding),
+                    None => page.body.clone(),
+                };
+                let words: Vec<&str> = body.split_whitespace().collect();
+                tantivy_document.add_text(schema.ngrams, words.join(" "));
+            }
+            tantivy_document.add_text(schema.doc_id, doc_id);
+            if let Some(document_title) = &metadata.title {
+                tantivy_document.add_text(schema.document_title, document_title);
+            }
+            if let Some(author) = &metadata.author {
+                tantivy_document.add_text(schema.author, author);
+            }
+            if let Some(subject) = &metadata.subject {
+                tantivy_document.add_text(schema.subject, subject);
+            }
+            if let Some(keywords) = &metadata.keywords {
+                tantivy_document.add_text(schema.keywords, keywords);
+            }
+            if let Some(year) = metadata.year {
+                tantivy_document.add_u64(schema.year, year);
+            }
+            if let Some(citekey) = &metadata.citekey {
+                tantivy_document.add_text(schema.citekey, citekey);
+            }
+            if let Some(section) = &page.section {
+                tantivy_document.add_text(schema.section, section);
+            }
+            if let Some(line) = page.line {
+                tantivy_document.add_u64(schema.line, line);
+            }
             writer.add_document(tantivy_document)?;
             Ok(())
         } else {
@@ -415,58 +1179,65 @@ impl Index {
         }
     }
 
-    fn open_checksum_map(&self) -> Result<HashMap<String, (u64, SystemTime)>> {
-        if let Index::Writing { documents_path, .. } = self {
-            let path = documents_path
-                .join(LITT_DIRECTORY_NAME)
-                .join(CHECK_SUM_MAP_FILENAME);
-            let data = std::fs::read_to_string(path)?;
-            Ok(serde_json::from_str(&data)?)
+    /// Reads the checksum map of the documents in the last commit.
+    fn open_checksum_map(&self) -> Result<HashMap<String, DocumentChecksum>> {
+        if let Index::Writing { index, .. } = self {
+            match index.load_metas()?.payload {
+                Some(data) => Ok(serde_json::from_str(&data)?),
+                None => Ok(HashMap::new()),
+            }
         } else {
             Err(StateError("Writing".to_string()))
         }
     }
 
-    fn store_checksum_map(&self, checksum_map: HashMap<String, (u64, SystemTime)>) -> Result<()> {
-        if let Index::Writing { documents_path, .. } = self {
-            let path = documents_path
-                .join(LITT_DIRECTORY_NAME)
-                .join(CHECK_SUM_MAP_FILENAME);
-            std::fs::write(path, serde_json::to_string(&checksum_map)?).map_err(Into::into)
+    /// Commits all changes. The checksum map of all committed documents is stored with the
+    /// commit, so it always matches the documents in the index.
+    fn commit(&mut self, checksum_map: &HashMap<String, DocumentChecksum>) -> Result<()> {
+        if let Index::Writing { writer, .. } = self {
+            let mut prepared_commit = writer.prepare_commit()?;
+            prepared_commit.set_payload(&serde_json::to_string(checksum_map)?);
+            prepared_commit.commit()?;
+            Ok(())
         } else {
             Err(StateError("Writing".to_string()))
         }
     }
 
-    /// Calculates the checksum of a file that consists of the metadata length and last modified time
-    fn calculate_checksum(path: &str) -> Result<(String, (u64, SystemTime))> {
-        let file = File::open(path)?;
+    /// Calculates the checksum of a file: its length and modification time, which are compared to
+    /// skip unchanged files quickly, and the SHA-256 hash of its content. Files modified shortly
+    /// before they were hashed are hashed again by the next update (see
+    /// [DocumentChecksum::hashed_after_modification]).
+    fn calculate_checksum(path: &str, doc_id: String) -> Result<(String, DocumentChecksum)> {
+        // Taken before reading, so changes while hashing count as made after it.
+        let hashed = SystemTime::now();
+        let mut file = File::open(path)?;
         let metadata = file.metadata()?;
         let modified = metadata.modified()?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher)?;
 
-        let result = (path.to_string(), (metadata.len(), modified));
-        Ok(result)
+        let checksum = DocumentChecksum {
+            len: metadata.len(),
+            modified,
+            doc_id,
+            hash: Some(format!("{:x}", hasher.finalize())),
+            hashed: Some(hashed),
+        };
+        Ok((path.to_string(), checksum))
     }
 
-    fn checksum_is_equal(path: &str, checksum: Option<&(u64, SystemTime)>) -> Result<bool> {
-        if let Some((len, last_modified)) = checksum {
+    fn checksum_is_equal(path: &str, checksum: Option<&DocumentChecksum>) -> Result<bool> {
+        if let Some(checksum) = checksum {
             let file = File::open(path)?;
             let metadata = file.metadata()?;
             let modified = metadata.modified()?;
-            Ok(*len == metadata.len() && *last_modified == modified)
+            Ok(checksum.len == metadata.len() && checksum.modified == modified)
         } else {
             Ok(false)
         }
     }
 
-    fn store_page_index(path: &Path, pindex: PageIndex) -> Result<()> {
-        // Create reversed index map
-        let path = path.with_extension("pageindex");
-        let json_str = serde_json::to_string(&pindex)?;
-        std::fs::write(path, json_str)?;
-        Ok(())
-    }
-
     fn create_page_index(body: &str) -> Result<PageIndex> {
         let mut pindex: PageIndex = HashMap::new();
         let mut i = 0;
@@ -496,10 +1267,15 @@ impl Index {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{Folding, Language};
     use litt_shared::test_helpers::cleanup_dir_and_file;
     use once_cell::sync::Lazy;
     use serial_test::serial;
+    use std::fs;
     use std::panic;
+    use tantivy::collector::{Count, TopDocs};
+    use tantivy::query::AllQuery;
+    use tantivy::schema::Value;
 
     const TEST_DIR_NAME: &str = "resources";
     const TEST_FILE_PATH: &str = "test.pdf";
@@ -521,6 +1297,23 @@ mod tests {
         assert!(result.is_ok())
     }
 
+    /// Returns the ids of the documents in the index.
+    fn doc_ids(index: &Index) -> HashSet<String> {
+        let searcher = index.searcher().unwrap();
+        let addresses = searcher.search(&AllQuery, &DocSetCollector).unwrap();
+        addresses
+            .into_iter()
+            .map(|address| {
+                let stored: TantivyDocument = searcher.doc(address).unwrap();
+                stored
+                    .get_first(SEARCH_SCHEMA.doc_id)
+                    .and_then(|doc_id| doc_id.as_str())
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
     #[test]
     #[serial]
     fn test_create() {
@@ -565,6 +1358,807 @@ mod tests {
         });
     }
 
+    #[test]
+    #[serial]
+    fn test_update_removes_deleted_documents() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            let kept_path = Path::new(TEST_DIR_NAME).join("kept.md");
+            let deleted_path = Path::new(TEST_DIR_NAME).join("deleted.txt");
+            fs::write(&kept_path, "Hello beautiful World").unwrap();
+            fs::write(&deleted_path, "Goodbye cruel World").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 2);
+            assert!(index.removed_documents().unwrap().is_empty());
+
+            fs::remove_file(&deleted_path).unwrap();
+            let index = index.update().unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 1);
+            assert_eq!(index.removed_documents().unwrap(), vec!["deleted.txt"]);
+            assert_eq!(doc_ids(&index).len(), 1);
+            // Page indices are stored with the pages, not as separate files.
+            assert!(!Path::new(TEST_DIR_NAME)
+                .join(LITT_DIRECTORY_NAME)
+                .join(PAGES_DIRECTORY_NAME)
+                .exists());
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_update_replaces_changed_documents() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            let document_path = Path::new(TEST_DIR_NAME).join("changed.md");
+            fs::write(&document_path, "Hello beautiful World").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            let old_doc_ids = doc_ids(&index);
+
+            fs::write(&document_path, "Goodbye cruel World").unwrap();
+            let index = index.update().unwrap();
+            let searcher = index.searcher().unwrap();
+            assert_eq!(searcher.num_docs(), 1);
+            let count = |term: &str| {
+                let query = index.query_parser().unwrap().parse_query(term).unwrap();
+                searcher.search(&query, &Count).unwrap()
+            };
+            assert_eq!(count("beautiful"), 0);
+            assert_eq!(count("cruel"), 1);
+            assert_eq!(old_doc_ids, doc_ids(&index));
+        });
+    }
+
+    /// Extractor failing for documents containing "broken".
+    struct FlakyExtractor;
+
+    impl Extractor for FlakyExtractor {
+        fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+            let body = fs::read_to_string(path)?;
+            match body.contains("broken") {
+                true => Err(crate::LittIndexError::UpdateError(body)),
+                false => Ok(vec![Page::from(body)]),
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_update_keeps_failed_documents() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            let document_path = Path::new(TEST_DIR_NAME).join("document.fake");
+            fs::write(&document_path, "Hello World").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .with_extractor("fake", FlakyExtractor)
+                .add_all_documents()
+                .unwrap();
+            let old_doc_ids = doc_ids(&index);
+
+            fs::write(&document_path, "broken").unwrap();
+            let index = index.update().unwrap();
+            assert_eq!(index.failed_documents().unwrap().len(), 1);
+            // The pages of the last version are kept.
+            assert_eq!(index.searcher().unwrap().num_docs(), 1);
+            assert_eq!(old_doc_ids, doc_ids(&index));
+
+            // Restoring the indexed content finds the document again.
+            fs::write(&document_path, "Hello World").unwrap();
+            let index = index.update().unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 1);
+            let index = index.update().unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 1);
+
+            // Indexed again with the same id, once it can be read.
+            fs::write(&document_path, "Goodbye World").unwrap();
+            let index = index.update().unwrap();
+            assert!(index.failed_documents().unwrap().is_empty());
+            assert_eq!(index.searcher().unwrap().num_docs(), 1);
+            assert_eq!(old_doc_ids, doc_ids(&index));
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_update_detects_changes_keeping_modification_time() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            let document_path = Path::new(TEST_DIR_NAME).join("changed.md");
+            fs::write(&document_path, "Hello World").unwrap();
+            let modified = fs::metadata(&document_path).unwrap().modified().unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+
+            // Changed within the timestamp granularity of the filesystem.
+            fs::write(&document_path, "Hallo Welt!").unwrap();
+            File::options()
+                .write(true)
+                .open(&document_path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+            let index = index.update().unwrap();
+            let searcher = index.searcher().unwrap();
+            let query = index.query_parser().unwrap().parse_query("Welt").unwrap();
+            assert_eq!(searcher.search(&query, &Count).unwrap(), 1);
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_update_detects_moved_documents() {
+        run_test(|| {
+            create_dir_all(Path::new(TEST_DIR_NAME).join("moved")).unwrap();
+            let old_path = Path::new(TEST_DIR_NAME).join("document.md");
+            let new_path = Path::new(TEST_DIR_NAME).join("moved").join("renamed.md");
+            fs::write(&old_path, "Hello beautiful World").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            let old_doc_ids = doc_ids(&index);
+
+            fs::rename(&old_path, &new_path).unwrap();
+            let index = index.update().unwrap();
+            assert!(index.removed_documents().unwrap().is_empty());
+            assert_eq!(doc_ids(&index), old_doc_ids);
+
+            let searcher = index.searcher().unwrap();
+            assert_eq!(searcher.num_docs(), 1);
+            let query = index
+                .query_parser()
+                .unwrap()
+                .parse_query("renamed")
+                .unwrap();
+            let top_docs = searcher.search(&query, &TopDocs::with_limit(1)).unwrap();
+            let doc: TantivyDocument = searcher.doc(top_docs[0].1).unwrap();
+            let title = doc.get_first(SEARCH_SCHEMA.title).unwrap();
+            assert_eq!(
+                title.as_str(),
+                Some(Path::new("moved").join("renamed.md").to_str().unwrap())
+            );
+            // The text of the page is taken from the stored document.
+            let body = doc.get_first(SEARCH_SCHEMA.body).unwrap();
+            assert_eq!(body.as_str().map(str::trim), Some("Hello beautiful World"));
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_ignore_files() {
+        run_test(|| {
+            let documents_path = Path::new(TEST_DIR_NAME);
+            create_dir_all(documents_path.join("drafts")).unwrap();
+            create_dir_all(documents_path.join("build")).unwrap();
+            fs::write(
+                documents_path.join(LITT_IGNORE_FILENAME),
+                "drafts/\n*.txt\n",
+            )
+            .unwrap();
+            fs::write(documents_path.join(".gitignore"), "build/\n").unwrap();
+            fs::write(documents_path.join("notes.md"), "Hello World").unwrap();
+            fs::write(documents_path.join("notes.txt"), "Hello World").unwrap();
+            fs::write(documents_path.join("notes.cmd"), "Hello World").unwrap();
+            fs::write(documents_path.join("drafts").join("draft.md"), "Hello").unwrap();
+            fs::write(documents_path.join("build").join("output.md"), "Hello").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone()).unwrap();
+            let mut files: Vec<String> = index
+                .collect_document_files()
+                .iter()
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect();
+            files.sort();
+            assert_eq!(files, vec!["notes.md", "output.md"]);
+            drop(index);
+            fs::remove_dir_all(documents_path.join(LITT_DIRECTORY_NAME)).unwrap();
+
+            let config = IndexConfig {
+                use_gitignore: true,
+                ..Default::default()
+            };
+            let index =
+                Index::create_with_config(TEST_DIR_NAME, SEARCH_SCHEMA.clone(), config).unwrap();
+            let files: Vec<String> = index
+                .collect_document_files()
+                .iter()
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect();
+            assert_eq!(files, vec!["notes.md"]);
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_empty_documents() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("empty.txt"), " \n\n").unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("notes.txt"), "Hello World").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            assert_eq!(index.empty_documents().unwrap(), vec!["empty.txt"]);
+            assert!(index.failed_documents().unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_resources() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("notes.md"), "Hello World").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .with_workers(1)
+                .with_writer_threads(1)
+                .unwrap()
+                .with_writer_memory(20_000_000)
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 1);
+            assert_eq!(index.config().workers, Some(1));
+            // Resources are not stored with the index.
+            assert_eq!(
+                IndexConfig::load(Path::new(TEST_DIR_NAME)).unwrap(),
+                IndexConfig::default()
+            );
+            drop(index);
+
+            // Every writer thread needs at least 15 MB.
+            let index = Index::open(TEST_DIR_NAME)
+                .unwrap()
+                .with_writer_memory(10_000_000)
+                .unwrap();
+            assert!(index.update().is_err());
+
+            // Without a memory limit, many threads get enough memory.
+            let index = Index::open(TEST_DIR_NAME)
+                .unwrap()
+                .with_writer_threads(8)
+                .unwrap();
+            assert!(index.update().is_ok());
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_cancel_and_resume() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            for name in ["one", "two", "three"] {
+                fs::write(Path::new(TEST_DIR_NAME).join(format!("{}.md", name)), name).unwrap();
+            }
+
+            // Cancel as soon as the first document was added. With a single worker, the other
+            // documents are not started.
+            let cancel_flag = Arc::new(AtomicBool::new(false));
+            let observed_flag = cancel_flag.clone();
+            let result = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .with_workers(1)
+                .with_cancel_flag(cancel_flag)
+                .with_observer(move |event| {
+                    if let IndexEvent::Added { .. } = event {
+                        observed_flag.store(true, AtomicOrdering::Relaxed);
+                    }
+                })
+                .add_all_documents();
+            assert!(matches!(result, Err(Cancelled)));
+            drop(result);
+
+            let index = Index::open(TEST_DIR_NAME).unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 1);
+
+            // The committed document is skipped, the others are added without duplicates.
+            let events = Arc::new(Mutex::new(vec![]));
+            let observed = events.clone();
+            let index = index
+                .with_observer(move |event| observed.lock().unwrap().push(event.clone()))
+                .update()
+                .unwrap();
+            let events = events.lock().unwrap();
+            let count = |matches: fn(&IndexEvent) -> bool| {
+                events.iter().filter(|event| matches(event)).count()
+            };
+            assert_eq!(
+                count(|event| matches!(event, IndexEvent::Skipped { .. })),
+                1
+            );
+            assert_eq!(count(|event| matches!(event, IndexEvent::Added { .. })), 2);
+            assert_eq!(index.searcher().unwrap().num_docs(), 3);
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_events() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            let notes_path = Path::new(TEST_DIR_NAME).join("notes.md");
+            fs::write(&notes_path, "# One\nHello\n# Two\nWorld").unwrap();
+
+            let events = Arc::new(Mutex::new(vec![]));
+            let observed = events.clone();
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .with_observer(move |event| observed.lock().unwrap().push(event.clone()))
+                .add_all_documents()
+                .unwrap();
+            assert_eq!(
+                events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                vec![
+                    IndexEvent::Started { documents: 1 },
+                    IndexEvent::PageExtracted {
+                        path: "notes.md".to_string(),
+                        page: 1,
+                        pages: 2
+                    },
+                    IndexEvent::PageExtracted {
+                        path: "notes.md".to_string(),
+                        page: 2,
+                        pages: 2
+                    },
+                    IndexEvent::Added {
+                        path: "notes.md".to_string(),
+                        pages: 2
+                    },
+                    IndexEvent::Committed {
+                        documents: 1,
+                        pages: 2,
+                        failed: 0,
+                        removed: 0
+                    },
+                ]
+            );
+
+            // The observer is kept when updating the index.
+            fs::remove_file(&notes_path).unwrap();
+            index.update().unwrap();
+            assert_eq!(
+                events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                vec![
+                    IndexEvent::Started { documents: 0 },
+                    IndexEvent::Removed {
+                        path: "notes.md".to_string()
+                    },
+                    IndexEvent::Committed {
+                        documents: 0,
+                        pages: 0,
+                        failed: 0,
+                        removed: 1
+                    },
+                ]
+            );
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_markdown_virtual_pages() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            let old_path = Path::new(TEST_DIR_NAME).join("notes.md");
+            let new_path = Path::new(TEST_DIR_NAME).join("renamed.md");
+            fs::write(&old_path, "# One\nHello World\n# Two\nGoodbye World").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 2);
+
+            // Sections and lines are kept when a document is moved
+            fs::rename(&old_path, &new_path).unwrap();
+            let index = index.update().unwrap();
+            let searcher = index.searcher().unwrap();
+            assert_eq!(searcher.num_docs(), 2);
+            let query = index
+                .query_parser()
+                .unwrap()
+                .parse_query("goodbye")
+                .unwrap();
+            let top_docs = searcher.search(&query, &TopDocs::with_limit(1)).unwrap();
+            let doc: TantivyDocument = searcher.doc(top_docs[0].1).unwrap();
+            let title = doc.get_first(SEARCH_SCHEMA.title).unwrap();
+            assert_eq!(title.as_str(), Some("renamed.md"));
+            let page = doc.get_first(SEARCH_SCHEMA.page).unwrap();
+            assert_eq!(page.as_u64(), Some(2));
+            let section = doc.get_first(SEARCH_SCHEMA.section).unwrap();
+            assert_eq!(section.as_str(), Some("Two"));
+            let line = doc.get_first(SEARCH_SCHEMA.line).unwrap();
+            assert_eq!(line.as_u64(), Some(3));
+        });
+    }
+
+    struct FakeExtractor;
+
+    impl Extractor for FakeExtractor {
+        fn extract(&self, _path: &Path) -> Result<Vec<Page>> {
+            Ok(vec![
+                Page::from("Hello World".to_string()),
+                Page::from("Goodbye World".to_string()),
+            ])
+        }
+
+        fn metadata(&self, _path: &Path) -> Result<DocumentMetadata> {
+            Ok(DocumentMetadata {
+                title: Some("Fake Document".to_string()),
+                author: Some("Max Weber".to_string()),
+                year: Some(1922),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_custom_extractor() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("document.fake"), "").unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("document.unknown"), "").unwrap();
+
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .with_extractor("fake", FakeExtractor)
+                .add_all_documents()
+                .unwrap();
+            let searcher = index.searcher().unwrap();
+            assert_eq!(searcher.num_docs(), 2);
+            let query = index
+                .query_parser()
+                .unwrap()
+                .parse_query("goodbye")
+                .unwrap();
+            let top_docs = searcher.search(&query, &TopDocs::with_limit(1)).unwrap();
+            let doc: TantivyDocument = searcher.doc(top_docs[0].1).unwrap();
+            let page = doc.get_first(SEARCH_SCHEMA.page).unwrap();
+            assert_eq!(page.as_u64(), Some(2));
+            let document_title = doc.get_first(SEARCH_SCHEMA.document_title).unwrap();
+            assert_eq!(document_title.as_str(), Some("Fake Document"));
+
+            // Metadata can be searched with field queries
+            for query in ["author:weber", "year:1922", "document_title:fake"] {
+                let query = index.query_parser().unwrap().parse_query(query).unwrap();
+                assert_eq!(searcher.search(&query, &Count).unwrap(), 2);
+            }
+        });
+    }
+
+    struct NoMetadataExtractor;
+
+    impl Extractor for NoMetadataExtractor {
+        fn extract(&self, path: &Path) -> Result<Vec<Page>> {
+            FakeExtractor.extract(path)
+        }
+
+        fn metadata(&self, _path: &Path) -> Result<DocumentMetadata> {
+            Err(crate::LittIndexError::PdfParseError(
+                "broken info".to_string(),
+            ))
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_unreadable_metadata() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("document.fake"), "").unwrap();
+
+            let events = Arc::new(Mutex::new(vec![]));
+            let observed = events.clone();
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .with_extractor("fake", NoMetadataExtractor)
+                .with_observer(move |event| observed.lock().unwrap().push(event.clone()))
+                .add_all_documents()
+                .unwrap();
+            // The document is indexed without metadata.
+            assert_eq!(index.searcher().unwrap().num_docs(), 2);
+            assert!(index.failed_documents().unwrap().is_empty());
+            assert!(events
+                .lock()
+                .unwrap()
+                .contains(&IndexEvent::MetadataFailed {
+                    path: "document.fake".to_string(),
+                    error: "Error parsing PDF: `broken info`".to_string(),
+                }));
+        });
+    }
+
+    struct FailingPageExtractor;
+
+    impl Extractor for FailingPageExtractor {
+        fn extract(&self, _path: &Path) -> Result<Vec<Page>> {
+            Ok(vec![
+                Page::from("Hello World".to_string()),
+                Page {
+                    error: Some("unreadable scan".to_string()),
+                    ..Default::default()
+                },
+            ])
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_failed_page() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("document.fake"), "").unwrap();
+
+            let events = Arc::new(Mutex::new(vec![]));
+            let observed = events.clone();
+            let index = Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .with_extractor("fake", FailingPageExtractor)
+                .with_observer(move |event| observed.lock().unwrap().push(event.clone()))
+                .add_all_documents()
+                .unwrap();
+            // The document is indexed with its other pages.
+            assert_eq!(index.searcher().unwrap().num_docs(), 2);
+            assert!(index.failed_documents().unwrap().is_empty());
+            assert!(events.lock().unwrap().contains(&IndexEvent::PageFailed {
+                path: "document.fake".to_string(),
+                page: 2,
+                error: "unreadable scan".to_string(),
+            }));
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_bibliography() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("document.fake"), "").unwrap();
+            let bib_file = Path::new(TEST_DIR_NAME).join("refs.bib");
+            fs::write(
+                &bib_file,
+                "@book{weber1922, title = {Wirtschaft und Gesellschaft}, file = {document.fake}}",
+            )
+            .unwrap();
+            let config = IndexConfig {
+                bib_file: Some(bib_file.clone()),
+                ..Default::default()
+            };
+
+            let index = Index::create_with_config(TEST_DIR_NAME, SEARCH_SCHEMA.clone(), config)
+                .unwrap()
+                .with_extractor("fake", FakeExtractor)
+                .add_all_documents()
+                .unwrap();
+            let searcher = index.searcher().unwrap();
+            let query = index
+                .query_parser()
+                .unwrap()
+                .parse_query("citekey:weber1922 AND document_title:gesellschaft")
+                .unwrap();
+            assert_eq!(searcher.search(&query, &Count).unwrap(), 2);
+
+            // Entries of the bibliography are kept when reloading
+            let index = index.reload().unwrap();
+            let searcher = index.searcher().unwrap();
+            assert_eq!(searcher.num_docs(), 2);
+            assert_eq!(searcher.search(&query, &Count).unwrap(), 2);
+
+            // A missing bibliography only fails indexing
+            fs::remove_file(&bib_file).unwrap();
+            drop(index);
+            let index = Index::open(TEST_DIR_NAME).unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 2);
+            let error = index.update().err().unwrap().to_string();
+            assert!(error.contains("bib_file"));
+            assert!(error.contains("refs.bib"));
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_migrate_outdated_index() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("notes.txt"), "Hello World").unwrap();
+            Index::create(TEST_DIR_NAME, SEARCH_SCHEMA.clone())
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            // Indices created before the format was versioned have no version file
+            let litt_path = Path::new(TEST_DIR_NAME).join(LITT_DIRECTORY_NAME);
+            fs::remove_file(litt_path.join(FORMAT_VERSION_FILENAME)).unwrap();
+            assert!(matches!(Index::open(TEST_DIR_NAME), Err(OutdatedIndex(0))));
+
+            Index::migrate(TEST_DIR_NAME)
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            let index = Index::open(TEST_DIR_NAME).unwrap();
+            assert_eq!(index.searcher().unwrap().num_docs(), 1);
+
+            fs::write(litt_path.join(FORMAT_VERSION_FILENAME), "999").unwrap();
+            assert!(matches!(Index::open(TEST_DIR_NAME), Err(OpenError(_))));
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_language() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(
+                Path::new(TEST_DIR_NAME).join("notes.txt"),
+                "Die Mystifizierung der Hunde",
+            )
+            .unwrap();
+            let config = IndexConfig {
+                language: Some(Language::German),
+                stopwords: true,
+                ..Default::default()
+            };
+            let index = Index::create_with_config(TEST_DIR_NAME, SEARCH_SCHEMA.clone(), config)
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            let searcher = index.searcher().unwrap();
+            let count = |term| {
+                let query = index.query_parser().unwrap().parse_query(term).unwrap();
+                searcher.search(&query, &Count).unwrap()
+            };
+            assert_eq!(count("Mystifizierungen"), 1);
+            assert_eq!(count("Hund"), 1);
+            assert_eq!(count("body:die"), 0);
+            drop(searcher);
+            drop(index);
+
+            // The tokenizer is registered again when opening the index
+            let index = Index::open(TEST_DIR_NAME).unwrap();
+            let query = index
+                .query_parser()
+                .unwrap()
+                .parse_query("Mystifizierungen")
+                .unwrap();
+            assert_eq!(index.searcher().unwrap().search(&query, &Count).unwrap(), 1);
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_folding() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(Path::new(TEST_DIR_NAME).join("notes.txt"), "Händel").unwrap();
+            let config = IndexConfig {
+                folding: Some(Folding::Umlaut),
+                ..Default::default()
+            };
+            let index = Index::create_with_config(TEST_DIR_NAME, SEARCH_SCHEMA.clone(), config)
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            let searcher = index.searcher().unwrap();
+            for term in ["Haendel", "Händel"] {
+                let query = index.query_parser().unwrap().parse_query(term).unwrap();
+                assert_eq!(searcher.search(&query, &Count).unwrap(), 1);
+            }
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_folded_ngrams() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(
+                Path::new(TEST_DIR_NAME).join("notes.txt"),
+                "Händel in der Straße",
+            )
+            .unwrap();
+            for folding in [Folding::Ascii, Folding::Umlaut] {
+                let config = IndexConfig {
+                    ngrams: true,
+                    folding: Some(folding),
+                    ..Default::default()
+                };
+                let index = Index::create_with_config(TEST_DIR_NAME, SEARCH_SCHEMA.clone(), config)
+                    .unwrap()
+                    .add_all_documents()
+                    .unwrap();
+                let searcher = index.searcher().unwrap();
+                // Every n-gram of the folded fragment is found
+                let count = |fragment| {
+                    let mut tokenizer = searcher
+                        .index()
+                        .tokenizer_for_field(SEARCH_SCHEMA.ngrams)
+                        .unwrap();
+                    let fragment = fold(fragment, folding);
+                    let mut token_stream = tokenizer.token_stream(&fragment);
+                    let mut counts = vec![];
+                    while let Some(token) = token_stream.next() {
+                        let term = Term::from_field_text(SEARCH_SCHEMA.ngrams, &token.text);
+                        let query = TermQuery::new(term, IndexRecordOption::Basic);
+                        counts.push(searcher.search(&query, &Count).unwrap());
+                    }
+                    counts
+                };
+                assert_eq!(count("strasse"), vec![1; 5]);
+                assert_eq!(count("Straße"), vec![1; 5]);
+                if folding == Folding::Umlaut {
+                    assert_eq!(count("haend"), vec![1; 3]);
+                }
+                drop(searcher);
+                drop(index);
+                fs::remove_dir_all(Path::new(TEST_DIR_NAME).join(LITT_DIRECTORY_NAME)).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_decompounding() {
+        run_test(|| {
+            create_dir_all(TEST_DIR_NAME).unwrap();
+            fs::write(
+                Path::new(TEST_DIR_NAME).join("notes.txt"),
+                "Einführung in die Literaturwissenschaft",
+            )
+            .unwrap();
+            let dictionary = Path::new(TEST_DIR_NAME).join("dictionary.dic");
+            fs::write(&dictionary, "Literatur\nWissenschaft\n").unwrap();
+            let config = IndexConfig {
+                compound_dictionary: Some(dictionary.clone()),
+                ..Default::default()
+            };
+            let index = Index::create_with_config(TEST_DIR_NAME, SEARCH_SCHEMA.clone(), config)
+                .unwrap()
+                .add_all_documents()
+                .unwrap();
+            let searcher = index.searcher().unwrap();
+            let count = |term| {
+                let query = index.query_parser().unwrap().parse_query(term).unwrap();
+                searcher.search(&query, &Count).unwrap()
+            };
+            assert_eq!(count("Wissenschaft"), 1);
+            assert_eq!(count("Literaturwissenschaft"), 1);
+            // Exact matches are still possible
+            assert_eq!(count("body:Wissenschaft"), 0);
+
+            // The dictionary is only needed for indexing
+            fs::remove_file(&dictionary).unwrap();
+            drop(index);
+            let index = Index::open(TEST_DIR_NAME).unwrap();
+            let searcher = index.searcher().unwrap();
+            let query = index
+                .query_parser()
+                .unwrap()
+                .parse_query("Wissenschaft")
+                .unwrap();
+            assert_eq!(searcher.search(&query, &Count).unwrap(), 1);
+            let error = index.update().err().unwrap().to_string();
+            assert!(error.contains("compound_dictionary"));
+            assert!(error.contains("dictionary.dic"));
+        });
+    }
+
     #[test]
     fn test_() {
         let text = "Hello*&%&^%, beautiful\n\rWörld!";
@@ -1,7 +1,15 @@
 use std::io;
 use thiserror::Error;
 
+pub mod bibliography;
+pub mod config;
+pub mod decompounding;
+pub mod event;
+pub mod extractor;
+pub mod folding;
 pub mod index;
+pub mod normalize;
+pub mod page_index;
 
 #[derive(Debug, Error)]
 pub enum LittIndexError {
@@ -11,18 +19,31 @@ pub enum LittIndexError {
     UpdateError(String),
     #[error("Error opening existing index: `{0}`")]
     OpenError(String),
+    #[error(
+        "Index was created by an older version of litt (format version {0}, current version {current}) and has to be re-indexed",
+        current = index::FORMAT_VERSION
+    )]
+    OutdatedIndex(u32),
     #[error("Error reloading index writer: `{0}`")]
     ReloadError(String),
     #[error("Index Write Error: `{0}`")]
     WriteError(String),
+    #[error("Indexing was cancelled. All documents indexed so far are kept, update the index to continue")]
+    Cancelled,
     #[error("Index is not in assumed state: `{0}`")]
     StateError(String),
     #[error("Index Read Error: `{0}`")]
     ReadError(String),
     #[error("Error parsing PDF: `{0}`")]
     PdfParseError(String),
+    #[error("Error parsing EPUB: `{0}`")]
+    EpubParseError(String),
+    #[error("Error parsing office document: `{0}`")]
+    OfficeParseError(String),
     #[error("Error parsing txt-file: `{0}`")]
     TxtParseError(String),
+    #[error("No extractor registered for document: `{0}`")]
+    UnsupportedFormat(String),
     #[error(transparent)]
     IoError(#[from] io::Error),
     #[error(transparent)]
@@ -0,0 +1,78 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Characters which break words at the end of a line.
+const HYPHENS: [char; 3] = ['-', '\u{2010}', '\u{00ad}'];
+
+/// Normalizes the extracted text of a page before it is indexed:
+/// - words hyphenated at the end of a line are rejoined ("my-\nstifiziert" is "mystifiziert"),
+/// - compatibility characters are replaced (NFKC), f.e. ligatures ("ﬁ" is "fi") and special
+///   whitespace,
+/// - soft hyphens, zero-width characters and control characters (except line breaks and tabs) are
+///   removed.
+pub fn normalize(text: &str) -> String {
+    let text = text.replace("\r\n", "\n");
+    let text: String = rejoin_hyphenated_words(&text).nfkc().collect();
+    text.chars()
+        .filter(|c| matches!(c, '\n' | '\t') || !(c.is_control() || is_invisible(*c)))
+        .collect()
+}
+
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00ad}' | '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{2060}' | '\u{feff}'
+    )
+}
+
+/// Removes hyphens and line breaks between the parts of words hyphenated at the end of a line. If
+/// the word continues with an uppercase letter (f.e. "Ost-\nWest"), the hyphen is kept.
+fn rejoin_hyphenated_words(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut joined = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if HYPHENS.contains(&c) && i > 0 && chars[i - 1].is_alphabetic() {
+            // Skip trailing whitespace, the line break and the indentation of the next line
+            let mut next = i + 1;
+            while next < chars.len() && matches!(chars[next], ' ' | '\t') {
+                next += 1;
+            }
+            if next < chars.len() && chars[next] == '\n' {
+                next += 1;
+                while next < chars.len() && chars[next].is_whitespace() && chars[next] != '\n' {
+                    next += 1;
+                }
+                if let Some(first) = chars.get(next).filter(|c| c.is_alphabetic()) {
+                    if first.is_uppercase() && c != '\u{00ad}' {
+                        joined.push('-');
+                    }
+                    i = next;
+                    continue;
+                }
+            }
+        }
+        joined.push(c);
+        i += 1;
+    }
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("my-\nstifiziert"), "mystifiziert");
+        assert_eq!(normalize("my-  \n   stifiziert"), "mystifiziert");
+        assert_eq!(normalize("my\u{00ad}\nstifiziert"), "mystifiziert");
+        assert_eq!(normalize("Ost-\nWest"), "Ost-West");
+        assert_eq!(normalize("Ost- und\nWest"), "Ost- und\nWest");
+        assert_eq!(normalize("- Punkt\n- Punkt"), "- Punkt\n- Punkt");
+        assert_eq!(normalize("2000-\n2010"), "2000-\n2010");
+        assert_eq!(normalize("Pro\u{fb01}t \u{fb02}ach"), "Profit flach");
+        assert_eq!(normalize("Mystifi\u{00ad}zierung"), "Mystifizierung");
+        assert_eq!(normalize("a\u{00a0}b\r\nc\u{0007}\td"), "a b\nc\td");
+    }
+}
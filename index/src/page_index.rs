@@ -0,0 +1,138 @@
+use crate::index::PageIndex;
+use crate::LittIndexError::ReadError;
+use crate::Result;
+
+/// Marks the start of every encoded page index.
+const MAGIC: &[u8; 3] = b"LPI";
+/// Version of the encoding, increased whenever the layout below changes.
+pub const PAGE_INDEX_VERSION: u8 = 1;
+
+/// Encodes the word positions of a page compactly.
+///
+/// Layout (all numbers are LEB128 varints): magic, version byte, number of words, then for every
+/// word (sorted) its byte length, its UTF-8 bytes, the number of its positions and for every
+/// position the distance of its start to the previous start and its length.
+pub fn encode(page_index: &PageIndex) -> Vec<u8> {
+    let mut words: Vec<_> = page_index.iter().collect();
+    words.sort_unstable_by_key(|(word, _)| word.as_str());
+
+    let mut data = Vec::with_capacity(MAGIC.len() + 1 + words.len() * 8);
+    data.extend_from_slice(MAGIC);
+    data.push(PAGE_INDEX_VERSION);
+    write_varint(&mut data, words.len() as u32);
+    for (word, positions) in words {
+        write_varint(&mut data, word.len() as u32);
+        data.extend_from_slice(word.as_bytes());
+        write_varint(&mut data, positions.len() as u32);
+        let mut previous_start = 0;
+        for &(start, end) in positions {
+            // Positions are in order of appearance, so the distances are small.
+            write_varint(&mut data, start.wrapping_sub(previous_start));
+            write_varint(&mut data, end.wrapping_sub(start));
+            previous_start = start;
+        }
+    }
+    data
+}
+
+/// Decodes a page index written by [`encode`].
+pub fn decode(data: &[u8]) -> Result<PageIndex> {
+    let mut reader = Reader { data, offset: 0 };
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(ReadError("Not a page index".to_string()));
+    }
+    let version = reader.bytes(1)?[0];
+    if version != PAGE_INDEX_VERSION {
+        return Err(ReadError(format!(
+            "Unsupported page index version {} (expected {})",
+            version, PAGE_INDEX_VERSION
+        )));
+    }
+    let word_count = reader.varint()?;
+    // Counts of corrupt data must not allocate more than the data can hold.
+    let mut page_index = PageIndex::with_capacity((word_count as usize).min(reader.remaining()));
+    for _ in 0..word_count {
+        let len = reader.varint()? as usize;
+        let word = std::str::from_utf8(reader.bytes(len)?)
+            .map_err(|e| ReadError(e.to_string()))?
+            .to_string();
+        let position_count = reader.varint()?;
+        let mut positions = Vec::with_capacity((position_count as usize).min(reader.remaining()));
+        let mut start = 0u32;
+        for _ in 0..position_count {
+            start = start.wrapping_add(reader.varint()?);
+            positions.push((start, start.wrapping_add(reader.varint()?)));
+        }
+        page_index.insert(word, positions);
+    }
+    Ok(page_index)
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        data.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| ReadError("Page index is truncated".to_string()))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u32> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ReadError("Invalid number in page index".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let mut page_index = PageIndex::new();
+        page_index.insert("Hello".to_string(), vec![(0, 5), (300, 305)]);
+        page_index.insert("Bär".to_string(), vec![(6, 9)]);
+        page_index.insert("".to_string(), vec![(5, 5), (9, 9)]);
+        let data = encode(&page_index);
+        assert_eq!(&data[..4], b"LPI\x01");
+        assert_eq!(decode(&data).unwrap(), page_index);
+
+        assert!(decode(&data[..data.len() - 1]).is_err());
+        let mut newer = data.clone();
+        newer[3] = PAGE_INDEX_VERSION + 1;
+        assert!(decode(&newer).is_err());
+        assert!(decode(b"{\"Hello\":[[0,5]]}").is_err());
+        // Counts larger than the data are not trusted
+        let mut corrupt = b"LPI\x01".to_vec();
+        write_varint(&mut corrupt, u32::MAX);
+        assert!(decode(&corrupt).is_err());
+        let mut corrupt = b"LPI\x01\x01\x01a".to_vec();
+        write_varint(&mut corrupt, u32::MAX);
+        assert!(decode(&corrupt).is_err());
+    }
+}
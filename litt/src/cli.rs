@@ -1,4 +1,11 @@
-use clap::Parser;
+use clap::builder::RangedU64ValueParser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use litt_index::config::{Folding, Language, PdfBackend};
+use litt_index::index::WRITER_MEMORY_PER_THREAD_BYTES;
+
+/// Bytes per MB of the memory options.
+pub const BYTES_PER_MB: usize = 1_000_000;
 
 /// Literature tool for searching pdfs in a directory (litt-index).
 #[derive(Parser, Debug)]
@@ -16,6 +23,77 @@ pub struct Cli {
     #[arg(short, long, value_name = "PATH", default_value_t = String::from(""))]
     pub init: String,
 
+    /// the backend used to extract text from PDFs of a new litt-index (auto, pdftotext, native)
+    #[arg(long, value_name = "BACKEND", default_value_t = PdfBackend::Auto)]
+    pub pdf_backend: PdfBackend,
+
+    /// the number of lines after which text and markdown files of a new litt-index start a new
+    /// page (0: split markdown files at headings only)
+    #[arg(long, value_name = "LINES", default_value_t = 100)]
+    pub lines_per_page: usize,
+
+    /// skip files ignored by .gitignore files in a new litt-index (.littignore files are always
+    /// honoured)
+    #[arg(long, default_value_t = false)]
+    pub gitignore: bool,
+
+    /// the file extensions to index in a new litt-index, f.e. "pdf,md" (default: all supported)
+    #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+    pub extensions: Vec<String>,
+
+    /// recognize the text of scanned PDF pages with tesseract in a new litt-index
+    #[arg(long, default_value_t = false)]
+    pub ocr: bool,
+
+    /// the language(s) tesseract recognizes when using --ocr, f.e. "deu+eng"
+    #[arg(long, value_name = "LANGUAGE")]
+    pub ocr_language: Option<String>,
+
+    /// the language of the documents of a new litt-index, f.e. "german": words are found in all
+    /// their forms ("Mystifizierung" also finds "Mystifizierungen")
+    #[arg(long, value_name = "LANGUAGE")]
+    pub language: Option<Language>,
+
+    /// leave out stopwords of the --language (f.e. "und", "der") in a new litt-index
+    #[arg(long, default_value_t = false, requires = "language")]
+    pub stopwords: bool,
+
+    /// fold letters with diacritics in a new litt-index: "ascii" ("Bar" finds "Bär") or "umlaut"
+    /// ("Haendel" finds "Händel")
+    #[arg(long, value_name = "FOLDING")]
+    pub fold: Option<Folding>,
+
+    /// split compounds in a new litt-index with the words of a dictionary file (one word per
+    /// line), so "Wissenschaft" also finds "Literaturwissenschaft"
+    #[arg(long, value_name = "PATH")]
+    pub compound_dictionary: Option<String>,
+
+    /// index the n-grams of a new litt-index, so fragments of words can be searched with
+    /// --substring
+    #[arg(long, default_value_t = false)]
+    pub ngrams: bool,
+
+    /// link the litt-index to a BibTeX/BibLaTeX file: documents listed in its `file` fields get
+    /// the citekey, authors, year and title of their entry (reloads an existing litt-index)
+    #[arg(long, value_name = "PATH")]
+    pub bib: Option<String>,
+
+    /// the number of documents extracted in parallel (default: one per CPU core), stored with a
+    /// new litt-index
+    #[arg(long, value_name = "NUMBER", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub workers: Option<usize>,
+
+    /// the memory of the index writer in MB (default: 100 or 15 per writer thread, if more; at
+    /// least 15 per writer thread), stored with a new litt-index
+    #[arg(long, value_name = "MB", value_parser = RangedU64ValueParser::<usize>::new()
+        .range(15..=(usize::MAX / BYTES_PER_MB) as u64))]
+    pub writer_memory: Option<usize>,
+
+    /// the number of threads of the index writer (default: one per CPU core, at most 8), stored
+    /// with a new litt-index
+    #[arg(long, value_name = "NUMBER", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub writer_threads: Option<usize>,
+
     /// updates an existing litt-index
     #[arg(short, long, default_value_t = false)]
     pub update: bool,
@@ -44,13 +122,58 @@ pub struct Cli {
     #[arg(short, long, default_value_t = false)]
     pub fuzzy: bool,
 
+    /// search for a fragment of a text, f.e. "lley si" finds "valley side" (requires a
+    /// litt-index created with --ngrams)
+    #[arg(short, long, default_value_t = false, conflicts_with = "fuzzy")]
+    pub substring: bool,
+
     /// the max distance between two terms when using --fuzzy. F.e. "bare"="bori" (distance=2)
     #[arg(long, long, default_value_t = 2)]
     pub distance: u8,
 }
 
+impl Cli {
+    /// Checks the options which depend on each other, which clap can not check on its own.
+    pub fn check(&self) -> Result<(), clap::Error> {
+        if let (Some(memory), Some(threads)) = (self.writer_memory, self.writer_threads) {
+            let min_memory = threads.saturating_mul(WRITER_MEMORY_PER_THREAD_BYTES / BYTES_PER_MB);
+            if memory < min_memory {
+                return Err(Cli::command().error(
+                    ErrorKind::ArgumentConflict,
+                    format!(
+                        "--writer-memory must be at least {} MB for {} writer threads",
+                        min_memory, threads
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[test]
 fn verify_cli() {
-    use clap::CommandFactory;
     Cli::command().debug_assert()
 }
+
+#[test]
+fn verify_writer_memory_in_bytes_fits() {
+    let max = usize::MAX / BYTES_PER_MB;
+    let cli = Cli::try_parse_from(["litt", "--writer-memory", &max.to_string()]).unwrap();
+    assert!(cli
+        .writer_memory
+        .unwrap()
+        .checked_mul(BYTES_PER_MB)
+        .is_some());
+    assert!(Cli::try_parse_from(["litt", "--writer-memory", &(max + 1).to_string()]).is_err());
+}
+
+#[test]
+fn verify_writer_memory_suffices_for_threads() {
+    let cli = Cli::try_parse_from(["litt", "--writer-threads", "8"]).unwrap();
+    assert!(cli.check().is_ok());
+    let cli = Cli::try_parse_from(["litt", "--writer-threads", "8", "--writer-memory", "120"]);
+    assert!(cli.unwrap().check().is_ok());
+    let cli = Cli::try_parse_from(["litt", "--writer-threads", "8", "--writer-memory", "100"]);
+    assert!(cli.unwrap().check().is_err());
+}
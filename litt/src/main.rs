@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::Instant;
 use std::{env, io};
 use unicode_segmentation::UnicodeSegmentation;
@@ -12,14 +14,20 @@ use clap::Parser;
 
 extern crate litt_search;
 use crossterm::cursor::MoveToColumn;
+use indicatif::{ProgressBar, ProgressStyle};
+use litt_index::bibliography::Bibliography;
+use litt_index::config::IndexConfig;
+use litt_index::event::IndexEvent;
+use litt_index::extractor::DocumentMetadata;
 use litt_index::index::Index;
+use litt_index::LittIndexError;
 use litt_search::search::Search;
 use litt_shared::LITT_DIRECTORY_NAME;
 
 mod cli;
 mod tracker;
 
-use cli::Cli;
+use cli::{Cli, BYTES_PER_MB};
 use tantivy::Searcher;
 use tracker::IndexTracker;
 
@@ -61,6 +69,7 @@ pub struct SearchOptions {
     limit: usize,
     offset: usize,
     fuzzy: bool,
+    substring: bool,
     distance: u8,
 }
 
@@ -110,6 +119,59 @@ Using standard system PDF viewer... {}",
     Ok(())
 }
 
+fn open_epub(path: String, chapter: u32) -> Result<(), LittError> {
+    // Every spine item is a page, so the chapter is the n-th itemref of the spine (/6).
+    let mut cmd = std::process::Command::new("ebook-viewer");
+    cmd.arg(format!("--open-at=epubcfi(/6/{})", chapter * 2))
+        .arg(&path);
+
+    let viewer_was_successful = match cmd.status() {
+        Ok(status) => match status.code() {
+            None => false,
+            Some(code) => code == 0,
+        },
+        Err(_) => false,
+    };
+    if !viewer_was_successful {
+        println!(
+            "Consider installing calibre's ebook-viewer so we can open the EPUB at the correct chapter for you.\n\
+Using standard system EPUB viewer... {}",
+            path
+        );
+        open_std_programm(path)?;
+    }
+    Ok(())
+}
+
+fn open_text(path: String, line: u32) -> Result<(), LittError> {
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR"));
+    let editor_was_successful = match editor {
+        Ok(editor) => {
+            // The editor may be given with arguments (f.e. "emacs -nw")
+            let mut editor = editor.split_whitespace();
+            match editor.next() {
+                Some(program) => std::process::Command::new(program)
+                    .args(editor)
+                    .arg(format!("+{}", line))
+                    .arg(&path)
+                    .status()
+                    .is_ok_and(|status| status.success()),
+                None => false,
+            }
+        }
+        Err(_) => false,
+    };
+    if !editor_was_successful {
+        println!(
+            "Consider setting $EDITOR so we can open the file at the correct line for you.\n\
+Using standard system program... {}",
+            path
+        );
+        open_std_programm(path)?;
+    }
+    Ok(())
+}
+
 fn open_std_programm(path: String) -> Result<(), LittError> {
     #[cfg(target_os = "macos")]
     std::process::Command::new("open").arg(&path).spawn()?;
@@ -127,6 +189,30 @@ fn open_std_programm(path: String) -> Result<(), LittError> {
     Ok(())
 }
 
+/// Describes the metadata of a document for the result header, f.e.
+/// "Max Weber (1922) – Soziologie [Macht, Herrschaft]".
+fn describe_metadata(metadata: &DocumentMetadata) -> Option<String> {
+    let mut description = match (&metadata.author, metadata.year) {
+        (Some(author), Some(year)) => format!("{} ({})", author, year),
+        (Some(author), None) => author.to_string(),
+        (None, Some(year)) => format!("({})", year),
+        (None, None) => String::new(),
+    };
+    if let Some(subject) = &metadata.subject {
+        if !description.is_empty() {
+            description.push_str(" – ");
+        }
+        description.push_str(subject);
+    }
+    if let Some(keywords) = &metadata.keywords {
+        if !description.is_empty() {
+            description.push(' ');
+        }
+        description.push_str(&format!("[{}]", keywords));
+    }
+    (!description.is_empty()).then_some(description)
+}
+
 fn show_failed_documents_error(index: &Index) {
     let failed_documents: Vec<String> = index.failed_documents().unwrap_or_default();
     if !failed_documents.is_empty() {
@@ -138,6 +224,18 @@ fn show_failed_documents_error(index: &Index) {
     }
 }
 
+fn show_empty_documents_warning(index: &Index) {
+    let empty_documents: Vec<String> = index.empty_documents().unwrap_or_default();
+    if !empty_documents.is_empty() {
+        let warning_message = format!(
+            "The following documents contain no text (scanned documents can be searched when \
+the index is created with --ocr):\n{}",
+            empty_documents.join("\n")
+        );
+        println!("{}", warning_message);
+    }
+}
+
 fn read(history: &mut Vec<String>) -> Result<InteractiveSearchInput, LittError> {
     terminal::enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -190,13 +288,11 @@ fn read(history: &mut Vec<String>) -> Result<InteractiveSearchInput, LittError>
                             }
                         }
                     }
-                    KeyCode::Up => {
-                        if index > 0 {
-                            index -= 1;
-                            input = history.get(index).unwrap().to_string();
-                            clear_and_print(&mut stdout, format!("> {}", input), true)?;
-                            stdout.flush()?;
-                        }
+                    KeyCode::Up if index > 0 => {
+                        index -= 1;
+                        input = history.get(index).unwrap().to_string();
+                        clear_and_print(&mut stdout, format!("> {}", input), true)?;
+                        stdout.flush()?;
                     }
                     KeyCode::Down => {
                         if history.len() > index + 1 {
@@ -299,6 +395,10 @@ fn fast_open_result(index_tracker: &IndexTracker, last_result_num: &u32) -> Resu
         Ok(path) => {
             if path.0.ends_with("pdf") {
                 open_pdf(path.0.clone(), path.1, path.2.clone())?;
+            } else if path.0.ends_with("epub") {
+                open_epub(path.0.clone(), path.1)?;
+            } else if path.0.ends_with("md") || path.0.ends_with("txt") {
+                open_text(path.0.clone(), path.1)?;
             } else {
                 open_std_programm(path.0.clone())?;
             }
@@ -324,6 +424,93 @@ fn list_indicies(index_tracker: &IndexTracker) -> Result<(), LittError> {
     Ok(())
 }
 
+/**
+ * Applies the resources for indexing given on the command line (overriding the ones stored with
+ * the index for this run)
+ */
+fn with_resources(mut index: Index, cli: &Cli) -> Result<Index, LittIndexError> {
+    if let Some(workers) = cli.workers {
+        index = index.with_workers(workers);
+    }
+    if let Some(memory) = cli.writer_memory {
+        index = index.with_writer_memory(memory * BYTES_PER_MB)?;
+    }
+    if let Some(threads) = cli.writer_threads {
+        index = index.with_writer_threads(threads)?;
+    }
+    Ok(index)
+}
+
+/// Set while documents are added to an index, so that Ctrl-C stops indexing gracefully.
+static INDEXING: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Flag set by the first Ctrl-C while indexing (a second one quits immediately)
+ */
+fn cancel_flag() -> Arc<AtomicBool> {
+    static CANCEL_FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    CANCEL_FLAG
+        .get_or_init(|| {
+            let cancel_flag = Arc::new(AtomicBool::new(false));
+            let handler_flag = cancel_flag.clone();
+            let result = ctrlc::set_handler(move || {
+                if !INDEXING.load(Ordering::SeqCst) || handler_flag.swap(true, Ordering::SeqCst) {
+                    std::process::exit(130);
+                }
+                eprintln!("\nStopping after the current documents (press Ctrl-C again to quit)...");
+            });
+            if let Err(e) = result {
+                eprintln!("Indexing can not be stopped gracefully: {}", e);
+            }
+            cancel_flag
+        })
+        .clone()
+}
+
+/**
+ * Shows the progress of adding documents to the index as progress bar and stops gracefully on
+ * Ctrl-C (committing all documents added so far)
+ */
+fn prepare_indexing(index: Index) -> Index {
+    INDEXING.store(true, Ordering::SeqCst);
+    let bar = ProgressBar::new(0).with_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} documents (ETA {eta}) {wide_msg}",
+        )
+        .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+    index
+        .with_observer(move |event| match event {
+            IndexEvent::Started { documents } => {
+                bar.set_length(*documents as u64);
+                bar.reset_eta();
+            }
+            IndexEvent::PageExtracted { path, page, pages } => {
+                bar.set_message(format!("{} (page {}/{})", path, page, pages))
+            }
+            IndexEvent::PageFailed { path, page, error } => bar.println(format!(
+                "{} {} of {}: {}",
+                "Could not extract the text of page".yellow(),
+                page,
+                path,
+                error
+            )),
+            IndexEvent::MetadataFailed { path, error } => bar.println(format!(
+                "{} {}: {}",
+                "Could not read the metadata of".yellow(),
+                path,
+                error
+            )),
+            IndexEvent::Committed { .. } => {
+                INDEXING.store(false, Ordering::SeqCst);
+                bar.finish_and_clear()
+            }
+            event if event.ends_document() => bar.inc(1),
+            _ => {}
+        })
+        .with_cancel_flag(cancel_flag())
+}
+
 /**
  * Create new litt index
  */
@@ -331,6 +518,7 @@ fn create_litt_index(
     index_tracker: &mut IndexTracker,
     index_name: String,
     rel_path: &String,
+    config: IndexConfig,
 ) -> Result<(), LittError> {
     let current_dir = env::current_dir()?;
     let path = current_dir.join(rel_path);
@@ -353,12 +541,12 @@ fn create_litt_index(
         return Err(LittError::General(e.to_string()));
     }
 
-    let mut index = match Index::create(&path, SearchSchema::default()) {
+    let mut index = match Index::create_with_config(&path, SearchSchema::default(), config) {
         Ok(index) => index,
         Err(e) => return Err(LittError::General(e.to_string())),
     };
 
-    index = match index.add_all_documents() {
+    index = match prepare_indexing(index).add_all_documents() {
         Ok(index_with_documents) => index_with_documents,
         Err(e) => return Err(LittError::General(e.to_string())),
     };
@@ -370,6 +558,7 @@ fn create_litt_index(
         start.elapsed()
     );
     show_failed_documents_error(&index);
+    show_empty_documents_warning(&index);
     Ok(())
 }
 
@@ -409,7 +598,7 @@ fn update_litt_index(
     println!("Updating index \"{}\".", index_name);
     let old_num_docs = searcher.num_docs();
     let start = Instant::now();
-    match index.update() {
+    match prepare_indexing(index).update() {
         Ok(ref updated_index) => {
             println!(
                 "Update done. Successfully indexed {} new document pages in {:?}. Now {} document pages.",
@@ -419,7 +608,15 @@ fn update_litt_index(
                 searcher
                     .num_docs(),
             );
+            let removed_documents = updated_index.removed_documents().unwrap_or_default();
+            if !removed_documents.is_empty() {
+                println!(
+                    "Removed {} deleted document(s) from the index.",
+                    removed_documents.len()
+                );
+            }
             show_failed_documents_error(updated_index);
+            show_empty_documents_warning(updated_index);
             Ok(())
         }
         Err(e) => Err(LittError::General(e.to_string())),
@@ -437,7 +634,7 @@ fn reload_litt_index(
     println!("Reloading index \"{}\".", index_name);
     let old_num_docs = searcher.num_docs();
     let start = Instant::now();
-    match index.reload() {
+    match prepare_indexing(index).reload() {
         Ok(index) => {
             println!(
                 "Reload done. Successfully indexed {} new document pages in {:?}. Now {} document pages.",
@@ -446,12 +643,40 @@ fn reload_litt_index(
                 searcher.num_docs(),
             );
             show_failed_documents_error(&index);
+            show_empty_documents_warning(&index);
             Ok(())
         }
         Err(e) => Err(LittError::General(e.to_string())),
     }
 }
 
+/**
+ * Migrate litt index created by an older version of litt (reloads *every* document)
+ */
+fn migrate_litt_index(index_path: &Path, index_name: &str, cli: &Cli) -> Result<Index, LittError> {
+    println!(
+        "Index \"{}\" was created by an older version of litt. Reloading all documents.",
+        index_name
+    );
+    let start = Instant::now();
+    let index = match Index::migrate(index_path)
+        .and_then(|index| with_resources(index, cli))
+        .map(prepare_indexing)
+        .and_then(Index::add_all_documents)
+    {
+        Ok(index) => index,
+        Err(e) => return Err(LittError::General(e.to_string())),
+    };
+    println!(
+        "Migration done. Successfully indexed {} document pages in {:?}.",
+        index.searcher()?.num_docs(),
+        start.elapsed()
+    );
+    show_failed_documents_error(&index);
+    show_empty_documents_warning(&index);
+    Ok(index)
+}
+
 /**
  * Searches for query in litt index
  */
@@ -474,6 +699,8 @@ fn search_litt_index(
     let start = Instant::now();
     let search_term = if opts.fuzzy {
         litt_search::search::SearchTerm::Fuzzy(term, opts.distance)
+    } else if opts.substring {
+        litt_search::search::SearchTerm::Substring(term)
     } else {
         litt_search::search::SearchTerm::Exact(term)
     };
@@ -487,30 +714,56 @@ fn search_litt_index(
     let mut res_counter = 1;
     for (title, pages) in &results {
         counter += 1;
-        let title_name = Path::new(title)
-            .with_extension("")
-            .to_string_lossy()
-            .to_string();
-        println!("{}. {}", counter, title_name.bold());
+        let metadata = match pages.front() {
+            Some(page) => search
+                .get_document_metadata(page)
+                .map_err(|e| LittError::General(e.to_string()))?,
+            None => DocumentMetadata::default(),
+        };
+        let title_name = metadata.title.clone().unwrap_or_else(|| {
+            Path::new(title)
+                .with_extension("")
+                .to_string_lossy()
+                .to_string()
+        });
+        match &metadata.citekey {
+            Some(citekey) => println!("{}. [{}] {}", counter, citekey.green(), title_name.bold()),
+            None => println!("{}. {}", counter, title_name.bold()),
+        }
+        if let Some(description) = describe_metadata(&metadata) {
+            println!("   {}", description);
+        }
         let index_path = index_path.join(title);
         println!("   ({})", index_path.to_string_lossy().italic());
+        // EPUBs are split into chapters instead of pages
+        let page_label = if title.ends_with("epub") { "ch." } else { "p." };
         for page in pages {
             let (preview, matched_term) = match search.get_preview(page, &search_term) {
                 Ok(preview) => preview,
                 Err(e) => return Err(LittError::General(e.to_string())),
             };
+            let (section, line) = search
+                .get_page_location(page)
+                .map_err(|e| LittError::General(e.to_string()))?;
+            let location = match (section, line) {
+                (Some(section), Some(line)) => format!("section {} / line {}", section, line),
+                (None, Some(line)) => format!("line {}", line),
+                _ => format!("{}{}", page_label, page.page),
+            };
+            // Documents split into virtual pages are opened at the line instead of the page
+            let position = line.and_then(|line| u32::try_from(line).ok());
             fast_store_results.insert(
                 res_counter,
                 (
                     index_path.to_string_lossy().to_string(),
-                    page.page,
+                    position.unwrap_or(page.page),
                     matched_term,
                 ),
             );
             println!(
-                "  - [{}] p.{}: \"{}\", (score: {})",
+                "  - [{}] {}: \"{}\", (score: {})",
                 res_counter,
-                page.page,
+                location,
                 preview.italic(),
                 page.score
             );
@@ -546,6 +799,9 @@ fn main() -> Result<(), LittError> {
     }
 
     let cli = Cli::parse();
+    if let Err(e) = cli.check() {
+        e.exit();
+    }
 
     // everything that does not require litt index
 
@@ -555,7 +811,7 @@ fn main() -> Result<(), LittError> {
     }
 
     // check if name of litt index was given by user
-    let index_name = match cli.litt_index {
+    let index_name = match cli.litt_index.clone() {
         None => {
             Cli::command().print_help()?;
             return Err(LittError::General("Litt index missing!".into()));
@@ -565,7 +821,30 @@ fn main() -> Result<(), LittError> {
 
     // initialize new index
     if !cli.init.is_empty() {
-        return create_litt_index(&mut index_tracker, index_name, &cli.init);
+        let current_dir = env::current_dir()?;
+        let bib_file = cli.bib.as_ref().map(|bib| current_dir.join(bib));
+        let compound_dictionary = cli
+            .compound_dictionary
+            .as_ref()
+            .map(|dictionary| current_dir.join(dictionary));
+        let config = IndexConfig {
+            pdf_backend: cli.pdf_backend,
+            lines_per_page: cli.lines_per_page,
+            use_gitignore: cli.gitignore,
+            extensions: (!cli.extensions.is_empty()).then(|| cli.extensions.clone()),
+            ocr: cli.ocr,
+            ocr_language: cli.ocr_language.clone(),
+            bib_file,
+            language: cli.language,
+            stopwords: cli.stopwords,
+            folding: cli.fold,
+            compound_dictionary,
+            ngrams: cli.ngrams,
+            workers: cli.workers,
+            writer_memory: cli.writer_memory.map(|mb| mb * BYTES_PER_MB),
+            writer_threads: cli.writer_threads,
+        };
+        return create_litt_index(&mut index_tracker, index_name, &cli.init, config);
     }
 
     // remove litt directory at index path
@@ -575,8 +854,29 @@ fn main() -> Result<(), LittError> {
 
     // get index:
     let index_path = index_tracker.get_path(&index_name)?;
-    let index = match Index::open(index_path.clone(), SearchSchema::default()) {
-        Ok(index) => index,
+    // link existing index to a bibliography (applied by reloading the index below)
+    if let Some(bib) = &cli.bib {
+        let bib_file = env::current_dir()?.join(bib);
+        // Otherwise, every later update would fail until the config is fixed by hand.
+        if let Err(e) = Bibliography::load(&bib_file) {
+            return Err(LittError::General(format!(
+                "Could not read the bibliography {}: {}",
+                bib_file.display(),
+                e
+            )));
+        }
+        let mut config = IndexConfig::load(&index_path)?;
+        config.bib_file = Some(bib_file);
+        config.store(&index_path)?;
+    }
+    let index = match Index::open(index_path.clone()) {
+        Ok(index) => match with_resources(index, &cli) {
+            Ok(index) => index,
+            Err(e) => return Err(LittError::General(e.to_string())),
+        },
+        Err(LittIndexError::OutdatedIndex(_)) => {
+            migrate_litt_index(&index_path, &index_name, &cli)?
+        }
         Err(e) => return Err(LittError::General(e.to_string())),
     };
     let searcher = index.searcher()?;
@@ -586,16 +886,18 @@ fn main() -> Result<(), LittError> {
         return update_litt_index(index, searcher, index_name.clone());
     }
     // reload existing index
-    if cli.reload {
+    if cli.reload || cli.bib.is_some() {
         return reload_litt_index(index, searcher, index_name.clone());
     }
-    let search = Search::new(index, SearchSchema::default());
+    let schema = index.schema().clone();
+    let search = Search::new(index, schema);
     // do normal search
     if !cli.term.is_empty() {
         let opts = SearchOptions {
             limit: cli.limit,
             offset: cli.offset,
             fuzzy: cli.fuzzy,
+            substring: cli.substring,
             distance: cli.distance,
         };
         return search_litt_index(
@@ -614,6 +916,7 @@ fn main() -> Result<(), LittError> {
         limit: 10,
         offset: 0,
         fuzzy: false,
+        substring: false,
         distance: 2,
     };
     let mut search_term = String::new();
@@ -623,7 +926,7 @@ fn main() -> Result<(), LittError> {
             println!(
                 "Interactive search in \"{}\" (limit={}, distance={}; type \"#set <variable> \
                 <value>\" to change, \"q\" to quit, start search-term with \"~\" for \
-                fuzzy-search)",
+                fuzzy-search or with \"*\" for substring-search)",
                 index_name.clone(),
                 opts.limit,
                 opts.distance
@@ -689,8 +992,9 @@ fn main() -> Result<(), LittError> {
                 continue;
             }
         }
-        let final_term = search_term.strip_prefix("~").unwrap_or(&search_term);
         opts.fuzzy = search_term.starts_with("~");
+        opts.substring = search_term.starts_with("*");
+        let final_term = search_term.strip_prefix(['~', '*']).unwrap_or(&search_term);
         match search_litt_index(
             &search,
             &mut index_tracker,
@@ -1,12 +1,16 @@
-use std::collections::{HashMap, LinkedList};
-use std::fs;
+use std::collections::{BTreeSet, HashMap, LinkedList};
 use tantivy::collector::TopDocs;
-use tantivy::schema::Value;
-use tantivy::{DocAddress, Snippet, SnippetGenerator, TantivyDocument};
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{IndexRecordOption, Value};
+use tantivy::{DocAddress, Snippet, SnippetGenerator, TantivyDocument, Term};
 
 extern crate litt_index;
+use litt_index::config::Folding;
+use litt_index::extractor::DocumentMetadata;
+use litt_index::folding::fold;
 use litt_index::index::{Index, PageIndex};
-use litt_shared::search_schema::SearchSchema;
+use litt_index::page_index;
+use litt_shared::search_schema::{SearchSchema, NGRAM_CHARS};
 
 use crate::LittSearchError::SearchError;
 use crate::Result;
@@ -14,6 +18,9 @@ use crate::Result;
 use levenshtein::levenshtein;
 
 const FUZZY_PREVIEW_NOT_FOUND: &str = "[fuzzy match] No preview. We're sry.";
+const SUBSTRING_PREVIEW_NOT_FOUND: &str = "[substring match] No preview. We're sry.";
+/// Number of characters shown before and after a substring match.
+const SUBSTRING_PREVIEW_CONTEXT: usize = 30;
 
 #[derive(Default)]
 struct FuzzyResult {
@@ -69,6 +76,8 @@ pub struct Search {
 pub enum SearchTerm {
     Fuzzy(String, u8),
     Exact(String),
+    /// Fragment of a text (f.e. "lley si" of "valley side"), only for indices with n-grams.
+    Substring(String),
 }
 
 fn get_first_term(query: &str) -> String {
@@ -96,16 +105,16 @@ impl Search {
     ) -> Result<HashMap<String, LinkedList<SearchResult>>> {
         let searcher = self.index.searcher()?;
 
-        let (query_parser, term) = match input {
+        let query = match input {
             SearchTerm::Fuzzy(term, distance) => {
                 let mut query_parser = self.index.query_parser()?;
                 query_parser.set_field_fuzzy(self.schema.body, true, *distance, true);
-                (query_parser, term)
+                query_parser.parse_query(term)?
             }
-            SearchTerm::Exact(term) => (self.index.query_parser()?, term),
+            SearchTerm::Exact(term) => self.index.query_parser()?.parse_query(term)?,
+            SearchTerm::Substring(fragment) => self.substring_query(fragment)?,
         };
 
-        let query = query_parser.parse_query(term)?;
         let top_docs = searcher.search(&query, &TopDocs::with_limit(limit).and_offset(offset))?;
 
         // Assemble results
@@ -149,6 +158,53 @@ impl Search {
         Ok(results)
     }
 
+    /// Returns the metadata of the document of the given result, as given by the document itself
+    /// (e.g. the author of a PDF or the `<title>` of a webpage) or by the linked bibliography.
+    pub fn get_document_metadata(&self, search_result: &SearchResult) -> Result<DocumentMetadata> {
+        let searcher = self.index.searcher()?;
+        let retrieved_doc: TantivyDocument = searcher.doc(DocAddress {
+            segment_ord: (search_result.segment_ord),
+            doc_id: (search_result.doc_id),
+        })?;
+        let text = |field| {
+            retrieved_doc
+                .get_first(field)
+                .and_then(|value| value.as_str())
+                .map(String::from)
+        };
+        Ok(DocumentMetadata {
+            title: text(self.schema.document_title),
+            author: text(self.schema.author),
+            subject: text(self.schema.subject),
+            keywords: text(self.schema.keywords),
+            year: retrieved_doc
+                .get_first(self.schema.year)
+                .and_then(|year| year.as_u64()),
+            citekey: text(self.schema.citekey),
+        })
+    }
+
+    /// Returns the section and line the page of the given result starts at, for documents split
+    /// into virtual pages (e.g. Markdown files).
+    pub fn get_page_location(
+        &self,
+        search_result: &SearchResult,
+    ) -> Result<(Option<String>, Option<u64>)> {
+        let searcher = self.index.searcher()?;
+        let retrieved_doc: TantivyDocument = searcher.doc(DocAddress {
+            segment_ord: (search_result.segment_ord),
+            doc_id: (search_result.doc_id),
+        })?;
+        let section = retrieved_doc
+            .get_first(self.schema.section)
+            .and_then(|section| section.as_str())
+            .map(String::from);
+        let line = retrieved_doc
+            .get_first(self.schema.line)
+            .and_then(|line| line.as_u64());
+        Ok((section, line))
+    }
+
     pub fn get_preview(
         &self,
         search_result: &SearchResult,
@@ -162,22 +218,30 @@ impl Search {
         })?;
 
         // Get text on given page
-        let path = retrieved_doc
-            .get_first(self.schema.path)
+        let page_index = retrieved_doc
+            .get_first(self.schema.page_index)
             .ok_or(SearchError(String::from(
-                "Fatal: Field \"path\" not found!",
+                "Fatal: Field \"page_index\" not found!",
             )))?
-            .as_str()
+            .as_bytes()
             .ok_or(SearchError(String::from(
-                "Fatal: Field \"path\" could not be read as text!",
+                "Fatal: Field \"page_index\" could not be read as bytes!",
             )))?;
-        let text = fs::read_to_string(path)?;
+        let text = retrieved_doc
+            .get_first(self.schema.body)
+            .and_then(|body| body.as_str())
+            .ok_or(SearchError(String::from(
+                "Fatal: Field \"body\" not found!",
+            )))?
+            .to_string();
 
         match search_term {
             SearchTerm::Fuzzy(term, _) => {
                 let mut cur: (String, String, f64) = ("".to_string(), "".to_string(), f64::MAX);
                 for t in term.split(" ").collect::<Vec<&str>>() {
-                    if let Ok((prev, matched_term, dist)) = self.get_fuzzy_preview(path, t, &text) {
+                    if let Ok((prev, matched_term, dist)) =
+                        self.get_fuzzy_preview(page_index, t, &text)
+                    {
                         if dist < cur.2 {
                             cur = (prev, matched_term, dist)
                         }
@@ -191,9 +255,53 @@ impl Search {
                                                                           // search
             }
             SearchTerm::Exact(term) => self.get_preview_from_query(term, text),
+            SearchTerm::Substring(fragment) => Ok(get_substring_preview(
+                fragment,
+                &text,
+                self.index.config().folding,
+            )),
         }
     }
 
+    /// Returns a query for pages containing all n-grams of the fragment. Since n-grams have no
+    /// positions, pages containing the n-grams in another order are found, too.
+    fn substring_query(&self, fragment: &str) -> Result<Box<dyn Query>> {
+        if !self.index.config().ngrams {
+            return Err(SearchError(
+                "Substring search requires an index created with n-grams (--ngrams)".to_string(),
+            ));
+        }
+        let searcher = self.index.searcher()?;
+        let mut tokenizer = searcher.index().tokenizer_for_field(self.schema.ngrams)?;
+        let words: Vec<&str> = fragment.split_whitespace().collect();
+        let mut fragment = words.join(" ");
+        // Folded before it is split into n-grams, like the text of the index
+        if let Some(folding) = self.index.config().folding {
+            fragment = fold(&fragment, folding);
+        }
+        let mut token_stream = tokenizer.token_stream(&fragment);
+        let mut ngrams = BTreeSet::new();
+        while let Some(token) = token_stream.next() {
+            ngrams.insert(token.text.clone());
+        }
+        if ngrams.is_empty() {
+            return Err(SearchError(format!(
+                "Substrings must have at least {} characters",
+                NGRAM_CHARS
+            )));
+        }
+        let queries: Vec<(Occur, Box<dyn Query>)> = ngrams
+            .iter()
+            .map(|ngram| {
+                let term = Term::from_field_text(self.schema.ngrams, ngram);
+                let query: Box<dyn Query> =
+                    Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+                (Occur::Must, query)
+            })
+            .collect();
+        Ok(Box::new(BooleanQuery::new(queries)))
+    }
+
     fn get_preview_from_query(&self, term: &str, text: String) -> Result<(String, String)> {
         let searcher = self.index.searcher()?;
         let query = self.index.query_parser()?.parse_query(term)?;
@@ -201,36 +309,31 @@ impl Search {
             .map_err(|e| SearchError(e.to_string()))?;
         snippet_generator.set_max_num_chars(70);
 
-        let snippet = snippet_generator.snippet(&text);
+        let mut snippet = snippet_generator.snippet(&text);
+        // Parts of compounds are only found in the decompounded body
+        if snippet.highlighted().is_empty() && self.index.config().compound_dictionary.is_some() {
+            let mut snippet_generator =
+                SnippetGenerator::create(&searcher, &*query, self.schema.decompounded)
+                    .map_err(|e| SearchError(e.to_string()))?;
+            snippet_generator.set_max_num_chars(70);
+            snippet = snippet_generator.snippet(&text);
+        }
         // let snippet = snippet_generator.snippet_from_doc(&retrieved_doc);
         Ok((self.highlight(snippet), get_first_term(term)))
     }
 
     fn get_fuzzy_preview(
         &self,
-        path: &str,
+        page_index: &[u8],
         term: &str,
         body: &str,
     ) -> Result<(String, String, f64)> {
-        let pindex: PageIndex = self
-            .index
-            .page_index(path)
-            .map_err(|_| SearchError("".to_string()))?;
+        let pindex: PageIndex =
+            page_index::decode(page_index).map_err(|_| SearchError("".to_string()))?;
         let res = self
             .get_fuzzy_match(term, pindex)
             .map_err(|_| SearchError("".to_string()))?;
-        // Another safe way to get substrings using char_indices
-        let start = body
-            .char_indices()
-            .nth(res.start.saturating_sub(20) as usize)
-            .unwrap_or((0, ' '))
-            .0;
-        let end = body
-            .char_indices()
-            .nth((res.end + 20) as usize)
-            .unwrap_or((body.len() - 1, ' '))
-            .0;
-        let substring = &format!("...{}...", &body[start..end]);
+        let substring = &format!("...{}...", fuzzy_preview_window(body, res.start, res.end));
         let substring = substring
             .to_string()
             .replace(&res.matched_term, &format!("**{}**", res.matched_term));
@@ -251,12 +354,20 @@ impl Search {
                 dist: f64::MAX,
                 ..Default::default()
             };
+            // Words are compared the way they are folded in the index
+            let folding = self.index.config().folding;
+            let fold_word = |word: &str| match folding {
+                Some(folding) => fold(word, folding),
+                None => word.to_string(),
+            };
+            let term = &fold_word(term);
             for (word, matches) in pindex {
-                let dist = cmp(&word, term);
+                let folded_word = fold_word(&word);
+                let dist = cmp(&folded_word, term);
                 // Use, if smaller than currently smallest distance.
                 // If distance is equal to currently smallest distance than use only if the
                 // inverse comparison is smaller then the current smallest distance.
-                if dist < cur.dist || (dist == cur.dist && cmp(&word, term) < cur.dist) {
+                if dist < cur.dist || (dist == cur.dist && cmp(&folded_word, term) < cur.dist) {
                     cur.dist = dist;
                     let (start, end) = matches.first().unwrap_or(&(0, 0));
                     cur = FuzzyResult {
@@ -292,6 +403,92 @@ impl Search {
     }
 }
 
+/// Returns the text from 20 characters before to 20 characters after the match at the given
+/// character positions (or up to the start or end of the text).
+fn fuzzy_preview_window(body: &str, start: u32, end: u32) -> &str {
+    // Safe way to get substrings using char_indices
+    let byte_index = |position: usize| {
+        body.char_indices()
+            .nth(position)
+            .map_or(body.len(), |(index, _)| index)
+    };
+    let start = byte_index(start.saturating_sub(20) as usize);
+    let end = byte_index(end as usize + 20);
+    &body[start..end.max(start)]
+}
+
+/// Returns the preview of the first occurrence of the fragment in the text (ignoring case, the
+/// given folding and treating any whitespace like a space) and the matched text.
+fn get_substring_preview(fragment: &str, text: &str, folding: Option<Folding>) -> (String, String) {
+    let Some((start, end)) = find_substring(text, fragment, folding) else {
+        // return empty string so that zathura does not search
+        return (SUBSTRING_PREVIEW_NOT_FOUND.to_string(), "".to_string());
+    };
+    let before: Vec<char> = text[..start]
+        .chars()
+        .rev()
+        .take(SUBSTRING_PREVIEW_CONTEXT)
+        .collect();
+    let before: String = before.into_iter().rev().collect();
+    let after: String = text[end..]
+        .chars()
+        .take(SUBSTRING_PREVIEW_CONTEXT)
+        .collect();
+    let matched = &text[start..end];
+    let preview = format!("...{} **{}** {}...", before, matched, after);
+    (preview.replace('\n', " "), matched.to_string())
+}
+
+/// Returns the byte range of the first occurrence of the fragment in the text. Text and fragment
+/// are compared lowercased and folded like the n-grams of the index.
+fn find_substring(text: &str, fragment: &str, folding: Option<Folding>) -> Option<(usize, usize)> {
+    let normalize = |text: &str| match folding {
+        Some(folding) => fold(text, folding).to_lowercase(),
+        None => text.to_lowercase(),
+    };
+    let words: Vec<&str> = fragment.split_whitespace().collect();
+    let pattern: Vec<char> = normalize(&words.join(" ")).chars().collect();
+    if pattern.is_empty() {
+        return None;
+    }
+    // The normalized characters of the text with the byte range of the original character they
+    // belong to (folding may turn one character into several, f.e. "ß" into "ss").
+    let chars: Vec<(char, usize, usize)> = text
+        .char_indices()
+        .flat_map(|(index, c)| {
+            let end = index + c.len_utf8();
+            normalize(&text[index..end])
+                .chars()
+                .map(|normalized| (normalized, index, end))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    'start: for start in 0..chars.len() {
+        // Matches start at the beginning of an original character
+        if start > 0 && chars[start - 1].1 == chars[start].1 {
+            continue;
+        }
+        let mut next = start;
+        for expected in &pattern {
+            // No later start can match, if the text ends before the pattern
+            let (c, _, _) = *chars.get(next)?;
+            next += 1;
+            if *expected == ' ' {
+                if !c.is_whitespace() {
+                    continue 'start;
+                }
+                while chars.get(next).is_some_and(|(c, _, _)| c.is_whitespace()) {
+                    next += 1;
+                }
+            } else if c != *expected {
+                continue 'start;
+            }
+        }
+        return Some((chars[start].1, chars[next - 1].2));
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use std::panic;
@@ -299,6 +496,7 @@ mod tests {
     use litt_shared::test_helpers::cleanup_litt_files;
 
     use super::*;
+    use litt_index::config::IndexConfig;
     const TEST_DIR_NAME: &str = "../resources";
     const TEST_DOC_NAME: &str = "test.pdf";
 
@@ -319,7 +517,12 @@ mod tests {
 
     fn create_searcher() -> Result<Search> {
         let search_schema = SearchSchema::default();
-        let index = Index::open_or_create(TEST_DIR_NAME, search_schema.clone()).unwrap();
+        let config = IndexConfig {
+            ngrams: true,
+            ..Default::default()
+        };
+        let index =
+            Index::create_with_config(TEST_DIR_NAME, search_schema.clone(), config).unwrap();
         let readable_index = index.add_all_documents()?;
         let searcher = readable_index.searcher()?;
         println!("loaded {} document pages.", searcher.num_docs());
@@ -332,10 +535,37 @@ mod tests {
             let search = create_searcher().unwrap();
             test_normal_search(&search);
             test_fuzzy_search(&search);
+            test_substring_search(&search);
             test_limit_and_offset(&search);
+            drop(search);
+            teardown();
+            test_folded_substring_search();
         })
     }
 
+    fn test_folded_substring_search() {
+        let search_schema = SearchSchema::default();
+        let config = IndexConfig {
+            ngrams: true,
+            folding: Some(Folding::Ascii),
+            ..Default::default()
+        };
+        let index = Index::create_with_config(TEST_DIR_NAME, search_schema.clone(), config)
+            .unwrap()
+            .add_all_documents()
+            .unwrap();
+        let search = Search::new(index, search_schema);
+        let search_term = &SearchTerm::Substring("Bar Hun".to_string());
+        let results = search.search(search_term, 0, 10).unwrap();
+        let doc_results = results.get(TEST_DOC_NAME).unwrap();
+        assert_eq!(doc_results.len(), 1);
+        let (preview, matched) = search
+            .get_preview(doc_results.front().unwrap(), search_term)
+            .unwrap();
+        assert_eq!(matched, "Bär Hün");
+        assert!(preview.contains("**Bär Hün**"));
+    }
+
     fn test_normal_search(search: &Search) {
         let test_cases: HashMap<&str, Vec<u32>> = HashMap::from([
             ("flooding", vec![2]),
@@ -428,6 +658,70 @@ mod tests {
         }
     }
 
+    fn test_substring_search(search: &Search) {
+        let test_cases: HashMap<&str, Vec<(u32, &str)>> = HashMap::from([
+            ("lley si", vec![(2, "**lley si**")]),
+            (
+                "ystifizier",
+                vec![(1, "**ystifizier**"), (2, "**ystifizier**")],
+            ),
+            ("ündi", vec![(1, "**ündi**")]),
+        ]);
+        for (fragment, pages) in &test_cases {
+            println!("- [substring] searching {}.", fragment);
+            let search_term = &SearchTerm::Substring(fragment.to_string());
+            let results = search.search(search_term, 0, 10).unwrap();
+            let doc_results = results.get(TEST_DOC_NAME).unwrap();
+            assert_eq!(pages.len(), doc_results.len());
+            for result in doc_results {
+                let (_, preview_part) =
+                    pages.iter().find(|(page, _)| *page == result.page).unwrap();
+                let (preview, _) = search.get_preview(result, search_term).unwrap();
+                assert!(preview.contains(preview_part));
+            }
+        }
+        assert!(search
+            .search(&SearchTerm::Substring("ll".to_string()), 0, 10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_find_substring() {
+        assert_eq!(
+            find_substring("the valley\n side", "LLEY SI", None),
+            Some((6, 14))
+        );
+        assert_eq!(find_substring("Bär", "är", None), Some((1, 4)));
+        assert_eq!(find_substring("Bär", "ar", None), None);
+        assert_eq!(find_substring("valley", "valleys", None), None);
+        // Folded like the n-grams of the index
+        assert_eq!(
+            find_substring("Der Bär", "bar", Some(Folding::Ascii)),
+            Some((4, 8))
+        );
+        assert_eq!(
+            find_substring("Die Straße", "STRASSE", Some(Folding::Ascii)),
+            Some((4, 11))
+        );
+        assert_eq!(
+            find_substring("Händel", "haend", Some(Folding::Umlaut)),
+            Some((0, 5))
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_preview_window() {
+        // Pages may end with a multibyte character (f.e. trimmed HTML pages).
+        assert_eq!(fuzzy_preview_window("Die Straße", 4, 10), "Die Straße");
+        let body = "Am Anfang war das Wort und das Wort war bei der Straße";
+        assert_eq!(
+            fuzzy_preview_window(body, 30, 34),
+            "war das Wort und das Wort war bei der Straße"
+        );
+        assert_eq!(fuzzy_preview_window(body, 0, 2), "Am Anfang war das Wort");
+        assert_eq!(fuzzy_preview_window("", 0, 0), "");
+    }
+
     fn test_limit_and_offset(search: &Search) {
         // river is contained twice
         let results = search
@@ -1,26 +1,109 @@
 use tantivy::schema::Field;
 use tantivy::schema::*;
 
+/// Name of the tokenizer of the body. It has to be registered on every opened index, since it
+/// depends on the settings (f.e. the language) of the index.
+pub const BODY_TOKENIZER: &str = "litt_body";
+/// Name of the tokenizer of the decompounded body, which adds the parts of compounds.
+pub const DECOMPOUNDED_TOKENIZER: &str = "litt_decompounded";
+/// Name of the tokenizer of the n-grams of the body.
+pub const NGRAM_TOKENIZER: &str = "litt_ngram";
+/// Number of characters of the n-grams of the body, the minimum length of substring searches.
+pub const NGRAM_CHARS: usize = 3;
+
 #[derive(Clone)]
 pub struct SearchSchema {
     pub title: Field,
-    pub path: Field,
+    /// Positions of the words of the page, used to find fuzzy matches for previews. Encoded by
+    /// `litt_index::page_index`.
+    pub page_index: Field,
     pub page: Field,
     pub body: Field,
+    /// Id shared by all pages of a document, used to delete a document's pages from the index.
+    pub doc_id: Field,
+    /// Title of the document as given by the document itself (e.g. the `<title>` of a webpage).
+    pub document_title: Field,
+    pub author: Field,
+    pub subject: Field,
+    pub keywords: Field,
+    /// Year the document was created.
+    pub year: Field,
+    /// Heading of the section a virtual page (e.g. of a Markdown file) belongs to.
+    pub section: Field,
+    /// Line a virtual page starts at.
+    pub line: Field,
+    /// Key of the document's entry in the bibliography linked to the index.
+    pub citekey: Field,
+    /// The body with the parts of compounds, only indexed if compounds are split.
+    pub decompounded: Field,
+    /// The n-grams of the body, used to find substrings. Only indexed if enabled.
+    pub ngrams: Field,
     pub schema: Schema,
 }
 
 impl SearchSchema {
-    pub fn new(title: Field, path: Field, page: Field, body: Field, schema: Schema) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        title: Field,
+        page_index: Field,
+        page: Field,
+        body: Field,
+        doc_id: Field,
+        document_title: Field,
+        author: Field,
+        subject: Field,
+        keywords: Field,
+        year: Field,
+        section: Field,
+        line: Field,
+        citekey: Field,
+        decompounded: Field,
+        ngrams: Field,
+        schema: Schema,
+    ) -> Self {
         Self {
             title,
-            path,
+            page_index,
             page,
             body,
+            doc_id,
+            document_title,
+            author,
+            subject,
+            keywords,
+            year,
+            section,
+            line,
+            citekey,
+            decompounded,
+            ngrams,
             schema,
         }
     }
 
+    /// Rebuilds the search schema from the schema of an existing index. Fails if a field is
+    /// missing, f.e. for indices created by an older version of litt.
+    pub fn from_schema(schema: Schema) -> tantivy::Result<Self> {
+        Ok(Self {
+            title: schema.get_field("title")?,
+            page_index: schema.get_field("page_index")?,
+            page: schema.get_field("page")?,
+            body: schema.get_field("body")?,
+            doc_id: schema.get_field("doc_id")?,
+            document_title: schema.get_field("document_title")?,
+            author: schema.get_field("author")?,
+            subject: schema.get_field("subject")?,
+            keywords: schema.get_field("keywords")?,
+            year: schema.get_field("year")?,
+            section: schema.get_field("section")?,
+            line: schema.get_field("line")?,
+            citekey: schema.get_field("citekey")?,
+            decompounded: schema.get_field("decompounded")?,
+            ngrams: schema.get_field("ngrams")?,
+            schema,
+        })
+    }
+
     pub fn default_fields(&self) -> Vec<Field> {
         vec![self.title, self.body]
     }
@@ -30,15 +113,56 @@ impl Default for SearchSchema {
     fn default() -> Self {
         let mut schema_builder = Schema::builder();
         let title = schema_builder.add_text_field("title", TEXT | STORED);
-        let path = schema_builder.add_text_field("path", TEXT | STORED);
+        let page_index = schema_builder.add_bytes_field("page_index", STORED);
         let page = schema_builder.add_u64_field("page", STORED);
-        let body = schema_builder.add_text_field("body", TEXT);
+        // The body is stored (compressed) to show previews
+        let body_options = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(BODY_TOKENIZER)
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            )
+            .set_stored();
+        let body = schema_builder.add_text_field("body", body_options);
+        let doc_id = schema_builder.add_text_field("doc_id", STRING | STORED);
+        let document_title = schema_builder.add_text_field("document_title", TEXT | STORED);
+        let author = schema_builder.add_text_field("author", TEXT | STORED);
+        let subject = schema_builder.add_text_field("subject", TEXT | STORED);
+        let keywords = schema_builder.add_text_field("keywords", TEXT | STORED);
+        let year = schema_builder.add_u64_field("year", INDEXED | STORED);
+        let section = schema_builder.add_text_field("section", STORED);
+        let line = schema_builder.add_u64_field("line", STORED);
+        let citekey = schema_builder.add_text_field("citekey", STRING | STORED);
+        let decompounded_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(DECOMPOUNDED_TOKENIZER)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let decompounded = schema_builder.add_text_field("decompounded", decompounded_options);
+        // All n-grams have the same position, so positions are not indexed
+        let ngrams_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(NGRAM_TOKENIZER)
+                .set_index_option(IndexRecordOption::WithFreqs),
+        );
+        let ngrams = schema_builder.add_text_field("ngrams", ngrams_options);
         let schema = schema_builder.build();
         Self {
             title,
-            path,
+            page_index,
             page,
             body,
+            doc_id,
+            document_title,
+            author,
+            subject,
+            keywords,
+            year,
+            section,
+            line,
+            citekey,
+            decompounded,
+            ngrams,
             schema,
         }
     }